@@ -1,33 +1,362 @@
+pub(crate) mod builder;
+pub mod ech;
 pub mod impersonate;
-pub use crate::ffi::ClientConfigFfi as ClientConfig;
+pub mod jar;
+pub mod redirect;
+pub mod session;
+
+pub use builder::{BrowserTemplate, ClientBuilder, Proxy};
+pub use ech::{DohResolver, EchConfigResolver, EchMode, EchStatus};
+pub use jar::{CookieStore, Jar, SiteContext};
+pub use redirect::{RedirectAction, RedirectAttempt, RedirectHistory, RedirectHop, RedirectPolicy};
+pub use session::{BrowserSession, ReferrerPolicy};
+
+use std::{
+    future::{poll_fn, Future},
+    pin::pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use bytes::Bytes;
+use http::{
+    header::{COOKIE, SET_COOKIE},
+    Response,
+};
+use publicsuffix::List;
 
 use crate::{
     client::impersonate::ImpersonationConfig,
-    ffi::{ReqwestxGoInit, ReqwestxGoInitImpl},
+    error::ErrorType,
+    ffi::{ClientConfigFfi, ReqwestxGo, ReqwestxGoImpl, ReqwestxGoInit, ReqwestxGoInitImpl},
+    request::Request,
+    response::Body,
 };
 
-/// Prepare the client, you should call this function before any other functions
-#[inline]
-pub fn init_client(config: ClientConfig) {
-    ReqwestxGoInitImpl::init_client(config);
+/// HTTP client, owning its own client instance on Go side.
+///
+/// Cloning a `Client` is cheap, all clones share the same Go side client, and the
+/// Go side resources will be released when the last clone is dropped.
+#[derive(Debug, Clone)]
+pub struct Client {
+    inner: Arc<ClientRef>,
+}
+
+#[derive(Debug)]
+struct ClientRef {
+    /// Handle id of the Go side client
+    id: u64,
+    /// Config of current client, kept in sync with Go side
+    state: RwLock<ClientState>,
 }
 
-/// Allow insecure connection
-#[inline]
-pub fn client_allow_insecure(data: bool) {
-    ReqwestxGoInitImpl::client_allow_insecure(data);
+#[derive(Debug)]
+struct ClientState {
+    proxy: Proxy,
+    allow_insecure: bool,
+    ech: EchMode,
+    cookie_store: Option<Arc<dyn CookieStore>>,
+    redirect: RedirectPolicy,
+    impersonation_config: Option<ImpersonationConfig>,
+}
+
+impl Drop for ClientRef {
+    fn drop(&mut self) {
+        // Nothing we can do if Go side fails to release it.
+        let _ = ReqwestxGoInitImpl::drop_client(self.id);
+    }
+}
+
+impl Client {
+    /// Create a new client with default config, see [`ClientBuilder`] for more options.
+    #[inline]
+    pub fn new() -> Result<Self, ErrorType> {
+        ClientBuilder::new().build()
+    }
+
+    #[inline]
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+
+    /// Create the Go side client instance, config should have been checked.
+    fn new_with_ffi(
+        config: ClientConfigFfi,
+        proxy: Proxy,
+        ech: EchMode,
+        cookie_store: Option<Arc<dyn CookieStore>>,
+        redirect: RedirectPolicy,
+    ) -> Result<Self, ErrorType> {
+        let state = ClientState {
+            proxy,
+            allow_insecure: config.allow_insecure,
+            ech,
+            cookie_store,
+            redirect,
+            impersonation_config: None,
+        };
+        let id = ReqwestxGoInitImpl::new_client(config).into_result()?;
+
+        Ok(Self {
+            inner: Arc::new(ClientRef {
+                id,
+                state: RwLock::new(state),
+            }),
+        })
+    }
+
+    /// Handle id of the Go side client
+    #[inline]
+    pub(crate) fn id(&self) -> u64 {
+        self.inner.id
+    }
+
+    /// Current proxy
+    pub fn proxy(&self) -> Proxy {
+        self.inner.state.read().unwrap().proxy.clone()
+    }
+
+    /// Whether insecure connection is allowed
+    pub fn is_insecure(&self) -> bool {
+        self.inner.state.read().unwrap().allow_insecure
+    }
+
+    /// Current ECH mode
+    pub fn ech_mode(&self) -> EchMode {
+        self.inner.state.read().unwrap().ech.clone()
+    }
+
+    /// Set ECH mode, which takes effect on new connections
+    pub fn set_ech_mode(&self, ech: EchMode) -> Result<(), ErrorType> {
+        let mut state = self.inner.state.write().unwrap();
+        ech.validate(state.impersonation_config.as_ref())?;
+        state.ech = ech;
+        Ok(())
+    }
+
+    /// Current cookie store, `None` if cookies are not handled
+    pub fn cookie_store(&self) -> Option<Arc<dyn CookieStore>> {
+        self.inner.state.read().unwrap().cookie_store.clone()
+    }
+
+    /// Set cookie store, `None` to stop handling cookies
+    pub fn set_cookie_store(&self, cookie_store: Option<Arc<dyn CookieStore>>) {
+        self.inner.state.write().unwrap().cookie_store = cookie_store;
+    }
+
+    /// Current default redirect policy
+    pub fn redirect_policy(&self) -> RedirectPolicy {
+        self.inner.state.read().unwrap().redirect.clone()
+    }
+
+    /// Set default redirect policy, used by requests without their own one
+    pub fn set_redirect_policy(&self, policy: RedirectPolicy) {
+        self.inner.state.write().unwrap().redirect = policy;
+    }
+
+    /// Current impersonation config, `None` if only pre-defined template is used
+    pub fn impersonation_config(&self) -> Option<ImpersonationConfig> {
+        self.inner
+            .state
+            .read()
+            .unwrap()
+            .impersonation_config
+            .clone()
+    }
+
+    /// Allow insecure connection
+    pub fn allow_insecure(&self, data: bool) -> Result<(), ErrorType> {
+        ReqwestxGoInitImpl::client_allow_insecure(self.id(), data).into_result()?;
+        self.inner.state.write().unwrap().allow_insecure = data;
+        Ok(())
+    }
+
+    /// Set proxy
+    pub fn set_proxy(&self, proxy: Proxy) -> Result<(), ErrorType> {
+        proxy.validate()?;
+
+        ReqwestxGoInitImpl::set_proxy_ffi(self.id(), proxy.to_ffi()).into_result()?;
+        self.inner.state.write().unwrap().proxy = proxy;
+        Ok(())
+    }
+
+    /// Update impersonation config
+    pub fn update_impersonation_config(
+        &self,
+        config: ImpersonationConfig,
+    ) -> Result<(), ErrorType> {
+        builder::validate_impersonation_config(&config)?;
+        self.ech_mode().validate(Some(&config))?;
+
+        ReqwestxGoInitImpl::update_impersonation_config(self.id(), config.clone().into())
+            .into_result()?;
+        self.inner.state.write().unwrap().impersonation_config = Some(config);
+        Ok(())
+    }
+
+    /// Send the request with current client
+    ///
+    /// The response body is streamed from Go side, see [`Body`]. Dropping the returned
+    /// future or the body will cancel the in-flight request on Go side. The response
+    /// carries an [`EchStatus`] in its extensions, along with the
+    /// [`GreaseValues`](impersonate::GreaseValues) of the connection if it's over TLS.
+    ///
+    /// With a [`CookieStore`], its cookies are sent unless the request has a `cookie`
+    /// header, and the `set-cookie` headers of the response are stored.
+    ///
+    /// Redirects are followed by the [`RedirectPolicy`] of the request or the client, and
+    /// the response carries a [`RedirectHistory`] with its final URL. A redirect keeping
+    /// the method of a streaming body can not be followed, and is returned as is. Timeout
+    /// of the request applies to each hop.
+    ///
+    /// On each hop, `authorization` and `proxy-authorization` are removed when leaving the
+    /// origin, `referer` is computed again by the referrer policy of [`BrowserSession`]
+    /// (strict-origin-when-cross-origin for other requests), and `sec-fetch-site` becomes
+    /// the least trusted relation of all hops, e.g. `same-site` after a redirect to another
+    /// subdomain. Sites are told by the public suffix list of [`BrowserSession`] for its
+    /// requests.
+    pub async fn execute(&self, mut request: Request) -> Result<Response<Body>, ErrorType> {
+        let policy = match request.redirect.take() {
+            Some(policy) => policy,
+            None => self.redirect_policy(),
+        };
+        let user_cookie = request.headers.contains_key(COOKIE);
+        let list = request
+            .extensions
+            .get::<Arc<List>>()
+            .cloned()
+            .unwrap_or_default();
+
+        let mut hops = Vec::new();
+        loop {
+            let url = request.uri.clone();
+            let mut next = request.clone_without_body();
+            let body = request.body.as_ref().map(|body| body.try_clone());
+
+            let mut response = self.execute_once(request).await?;
+            let Some(location) = redirect::redirect_location(&response, &url) else {
+                response.extensions_mut().insert(RedirectHistory {
+                    hops,
+                    final_url: url,
+                });
+                return Ok(response);
+            };
+
+            let status = response.status();
+            let attempt = RedirectAttempt {
+                status,
+                next: &location,
+                previous: &hops,
+            };
+            let follow = match policy.decide(&attempt) {
+                RedirectAction::Follow => true,
+                RedirectAction::Stop => false,
+                RedirectAction::Error(reason) => return Err(ErrorType::Redirect(reason)),
+            };
+            let keeps_body =
+                redirect::rewrite_request(&mut next, status, location, user_cookie, &list);
+            let replayable = match body {
+                Some(Some(body)) if keeps_body => {
+                    next.body = Some(body);
+                    true
+                }
+                Some(None) => !keeps_body,
+                _ => true,
+            };
+            if !(follow && replayable) {
+                response.extensions_mut().insert(RedirectHistory {
+                    hops,
+                    final_url: url,
+                });
+                return Ok(response);
+            }
+
+            hops.push(RedirectHop { url, status });
+            request = next;
+        }
+    }
+
+    /// Send a single request without following redirects
+    async fn execute_once(&self, mut request: Request) -> Result<Response<Body>, ErrorType> {
+        static REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+        let cookie_store = {
+            let state = self.inner.state.read().unwrap();
+            let config = state.impersonation_config.as_ref();
+            request.apply_kind_headers(config);
+            if let Some(store) = &state.cookie_store {
+                request.apply_cookies(store.as_ref(), config);
+            }
+            state.cookie_store.clone()
+        };
+        let url = cookie_store.as_ref().map(|_| request.uri.clone());
+
+        let ech_config_list = self.ech_mode().config_list(&request.uri).await?;
+        let ech_offered = ech_config_list.is_some();
+
+        let request_id = REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+        let (mut req_ffi, upload) = request.into_ffi(self.id(), request_id)?;
+        req_ffi.ech_config_list = ech_config_list.unwrap_or_default();
+
+        let mut guard = CancelGuard {
+            request_id,
+            done: false,
+        };
+        let mut send = pin!(ReqwestxGoImpl::send(req_ffi));
+        let result = match upload {
+            Some(upload) => {
+                // Keep uploading until the response arrives, the rest of the body is
+                // no longer needed then.
+                let mut upload = pin!(upload.run(request_id));
+                let mut uploaded = false;
+                poll_fn(|cx| {
+                    if !uploaded {
+                        uploaded = upload.as_mut().poll(cx).is_ready();
+                    }
+                    send.as_mut().poll(cx)
+                })
+                .await
+            }
+            None => send.await,
+        };
+        guard.done = true;
+
+        let mut response = result.into_result()?;
+        response.body_mut().keep_client(self);
+        if let (Some(store), Some(url)) = (cookie_store, url) {
+            let mut cookie_headers = response.headers().get_all(SET_COOKIE).iter();
+            store.set_cookies(&url, &mut cookie_headers);
+        }
+        let extensions = response.extensions_mut();
+        if ech_offered && extensions.get::<EchStatus>() == Some(&EchStatus::NotOffered) {
+            extensions.insert(EchStatus::Rejected);
+        }
+        Ok(response)
+    }
+
+    /// Send the request with current client, and read the whole response body into memory
+    pub async fn execute_buffered(&self, request: Request) -> Result<Response<Bytes>, ErrorType> {
+        let (parts, body) = self.execute(request).await?.into_parts();
+        let body = body.bytes().await?;
+
+        Ok(Response::from_parts(parts, body))
+    }
 }
 
-/// Set proxy
-#[inline]
-pub fn set_proxy(proxy: String) {
-    ReqwestxGoInitImpl::set_proxy_ffi(proxy);
+/// Cancel the Go side request if the future is dropped before it's done
+struct CancelGuard {
+    request_id: u64,
+    done: bool,
 }
 
-/// Update impersonation config
-#[inline]
-pub fn update_impersonation_config(config: ImpersonationConfig) {
-    ReqwestxGoInitImpl::update_impersonation_config(config.into());
+impl Drop for CancelGuard {
+    fn drop(&mut self) {
+        if !self.done {
+            let _ = ReqwestxGoImpl::cancel(self.request_id);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -38,29 +367,24 @@ mod tests {
 
     #[tokio::test]
     async fn test_client() {
-        let config = ClientConfig {
-            proxy: "socks5://127.0.0.1:9923".to_string(),
-            ..Default::default()
-        };
-        init_client(config);
+        let client = Client::builder()
+            .proxy(Proxy::Custom("socks5://127.0.0.1:9923".to_string()))
+            .build()
+            .unwrap();
 
         let uri = "https://api.myip.la/cn".parse().unwrap();
-        let response = Request::get(uri).execute().await.unwrap();
+        let response = client.execute(Request::get(uri)).await.unwrap();
         assert_eq!(response.status().as_u16(), 200);
         println!("response.version: {:?}", response.version());
         println!("response.headers: {:?}", response.headers());
 
-        let body = response.into_body();
-        let resp = String::from_utf8(body).unwrap();
+        let resp = response.into_body().text().await.unwrap();
         println!("response.body: {}", resp);
     }
 
     #[tokio::test]
     async fn test_custom_client() {
-        let config = ClientConfig {
-            ..Default::default()
-        };
-        init_client(config);
+        let client = Client::new().unwrap();
 
         let utls_spec = ClientHelloSpec::new()
             .set_cipher_suites(vec![
@@ -89,14 +413,14 @@ mod tests {
                     TlsExtension::ExtendedMasterSecret,
                     TlsExtension::RenegotiationInfo(1, vec![]),
                     TlsExtension::SupportedCurves(vec![
-                        CurveID::Custom(GREASE_PLACEHOLDER), // TLS Grease
+                        CurveID::Grease,
                         CurveID::X25519,
                         CurveID::CurveP256,
                         CurveID::CurveP384,
                     ]),
                     TlsExtension::SupportedPoints(vec![0x00]),
                     TlsExtension::SessionTicket,
-                    TlsExtension::ALPN(vec!["h2", "http/1.1"]),
+                    TlsExtension::ALPN(vec!["h2".to_string(), "http/1.1".to_string()]),
                     TlsExtension::StatusRequest,
                     TlsExtension::SignatureAlgorithms(vec![
                         SignatureScheme::ECDSAWithP256AndSHA256,
@@ -110,7 +434,7 @@ mod tests {
                     ]),
                     TlsExtension::SCT,
                     TlsExtension::KeyShare(vec![
-                        (CurveID::Custom(GREASE_PLACEHOLDER), vec![0x00]),
+                        (CurveID::Grease, vec![0x00]),
                         (CurveID::X25519, vec![]),
                     ]),
                     TlsExtension::PSKModes(vec![1]),
@@ -131,28 +455,14 @@ mod tests {
             spec: Some(utls_spec),
         };
 
-        let http2_settings_frame = vec![
-            Http2Setting {
-                setting_id: 0x1, // SettingHeaderTableSize
-                setting_val: 65536,
-            },
-            Http2Setting {
-                setting_id: 0x2, // SettingEnablePush
-                setting_val: 0,
-            },
-            Http2Setting {
-                setting_id: 0x3, // SettingMaxConcurrentStreams
-                setting_val: 1000,
-            },
-            Http2Setting {
-                setting_id: 0x4, // SettingInitialWindowSize
-                setting_val: 6291456,
-            },
-            Http2Setting {
-                setting_id: 0x6, // SettingMaxHeaderListSize
-                setting_val: 262144,
-            },
-        ];
+        let http2_settings_frame = Http2Settings::builder()
+            .header_table_size(65536)
+            .enable_push(0)
+            .max_concurrent_streams(1000)
+            .initial_window_size(6291456)
+            .max_header_list_size(262144)
+            .build()
+            .unwrap();
 
         let mut common_headers = HeaderMap::with_capacity(16);
         common_headers.insert("pragma", HeaderValue::from_static("no-cache"));
@@ -212,18 +522,101 @@ mod tests {
                 stream_dep: 0,
                 weight: 255,
             },
+            http2_priority_frames: Vec::new(),
+            request_kind_headers: Default::default(),
         };
 
-        update_impersonation_config(impersonate_config);
+        client
+            .update_impersonation_config(impersonate_config)
+            .unwrap();
 
         let uri = "https://tls.peet.ws/api/all".parse().unwrap();
-        let response = Request::get(uri).execute().await.unwrap();
+        let response = client.execute(Request::get(uri)).await.unwrap();
         assert_eq!(response.status().as_u16(), 200);
         println!("response.version: {:?}", response.version());
         println!("response.headers: {:?}", response.headers());
 
-        let body = response.into_body();
-        let resp = String::from_utf8(body).unwrap();
+        let resp = response.into_body().text().await.unwrap();
         println!("response.body: {}", resp);
     }
+
+    #[tokio::test]
+    async fn test_multiple_clients() {
+        let chrome = Client::builder()
+            .template(BrowserTemplate::Chrome)
+            .build()
+            .unwrap();
+        let firefox = Client::builder()
+            .template(BrowserTemplate::Firefox)
+            .build()
+            .unwrap();
+        assert_ne!(chrome.id(), firefox.id());
+
+        let uri: http::Uri = "https://tls.peet.ws/api/all".parse().unwrap();
+        let (chrome_resp, firefox_resp) = tokio::join!(
+            chrome.execute(Request::get(uri.clone())),
+            firefox.execute(Request::get(uri)),
+        );
+        let chrome_body = chrome_resp.unwrap().into_body().text().await.unwrap();
+        let firefox_body = firefox_resp.unwrap().into_body().text().await.unwrap();
+        assert_ne!(chrome_body, firefox_body);
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let client = Client::builder()
+            .connect_timeout(std::time::Duration::from_secs(10))
+            .build()
+            .unwrap();
+
+        let uri = "https://httpbin.org/delay/5".parse().unwrap();
+        let request = Request::get(uri).timeout(std::time::Duration::from_secs(1));
+        let result = client.execute(request).await;
+        assert!(matches!(result, Err(crate::error::ErrorType::Timeout)));
+    }
+
+    #[tokio::test]
+    async fn test_streaming_body() {
+        use futures_core::Stream;
+
+        let client = Client::new().unwrap();
+
+        let uri = "https://httpbin.org/stream-bytes/102400?chunk_size=1024"
+            .parse()
+            .unwrap();
+        let mut body = client.execute(Request::get(uri)).await.unwrap().into_body();
+
+        let mut total = 0;
+        while let Some(chunk) =
+            std::future::poll_fn(|cx| std::pin::Pin::new(&mut body).poll_next(cx)).await
+        {
+            total += chunk.unwrap().len();
+        }
+        assert_eq!(total, 102400);
+
+        let uri = "https://httpbin.org/bytes/1024".parse().unwrap();
+        let response = client.execute_buffered(Request::get(uri)).await.unwrap();
+        assert_eq!(response.body().len(), 1024);
+    }
+
+    #[tokio::test]
+    async fn test_streaming_upload() {
+        use crate::request::Body;
+
+        let client = Client::new().unwrap();
+
+        let uri: http::Uri = "https://httpbin.org/anything".parse().unwrap();
+        let reader: &'static [u8] = &[b'a'; 300 * 1024];
+        let request = Request::post(uri.clone()).set_body(Body::from_reader(reader, None));
+        let response = client.execute_buffered(request).await.unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        let resp = String::from_utf8_lossy(response.body());
+        assert!(resp.contains(r#""Transfer-Encoding": "chunked""#));
+
+        let request =
+            Request::post(uri).set_body(Body::from_reader(reader, Some(reader.len() as u64)));
+        let response = client.execute_buffered(request).await.unwrap();
+        let resp = String::from_utf8_lossy(response.body());
+        assert!(resp.contains(r#""Content-Length": "307200""#));
+    }
 }
@@ -0,0 +1,457 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures_core::future::BoxFuture;
+use http::{header, HeaderValue, Uri};
+
+use crate::{
+    client::{impersonate::ImpersonationConfig, Client},
+    error::ErrorType,
+    request::Request,
+};
+
+/// Version of ECHConfig supported by Go side, draft-ietf-tls-esni-18
+const ECH_VERSION: u16 = 0xfe0d;
+/// RR type of HTTPS record, see RFC 9460
+const DNS_TYPE_HTTPS: u16 = 65;
+/// SvcParamKey of ECHConfigList in HTTPS record
+const SVC_PARAM_ECH: u16 = 5;
+/// How long a host without ECHConfigList is cached if the answer has no TTL
+const NEGATIVE_TTL: u32 = 60;
+
+/// How Encrypted Client Hello is used.
+///
+/// Real ECH requires [`UtlsExtensionECH`](super::impersonate::TlsExtension::UtlsExtensionECH)
+/// in the ClientHelloSpec, whose place in the extension list is kept. Pre-defined templates
+/// are not checked for it, so [`EchMode::Config`] and [`EchMode::Resolver`] need an
+/// [`ImpersonationConfig`] with such a spec.
+#[derive(Debug, Clone, Default)]
+pub enum EchMode {
+    /// ECH extension in the ClientHelloSpec, if any, is GREASE. Default one.
+    #[default]
+    Grease,
+    /// Use the ECHConfigList for every HTTPS request, e.g. for a client talking to a
+    /// single server.
+    Config(Vec<u8>),
+    /// Look up ECHConfigList of each host, falling back to GREASE for hosts without one.
+    Resolver(Arc<dyn EchConfigResolver>),
+}
+
+impl EchMode {
+    /// Check the mode, along with the spec which will carry the ECH extension
+    pub(crate) fn validate(&self, config: Option<&ImpersonationConfig>) -> Result<(), ErrorType> {
+        if let Self::Config(config_list) = self {
+            validate_config_list(config_list)
+                .map_err(|e| ErrorType::InvalidConfig(format!("ech: {e}")))?;
+        }
+
+        let spec = config.and_then(|c| c.utls_config.spec.as_ref());
+        let offers_ech = spec.is_some_and(|spec| spec.offers_ech());
+        if matches!(self, Self::Config(_) | Self::Resolver(_)) && !offers_ech {
+            return Err(ErrorType::InvalidConfig(
+                "ech: ClientHelloSpec must contain UtlsExtensionECH".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// ECHConfigList to offer for the request, `None` for GREASE
+    pub(crate) async fn config_list(&self, uri: &Uri) -> Result<Option<Vec<u8>>, ErrorType> {
+        if uri.scheme() != Some(&http::uri::Scheme::HTTPS) {
+            return Ok(None);
+        }
+        match self {
+            Self::Grease => Ok(None),
+            Self::Config(config_list) => Ok(Some(config_list.clone())),
+            Self::Resolver(resolver) => {
+                let host = uri.host().unwrap_or_default();
+                let config_list = resolver
+                    .resolve(host, uri.port_u16().unwrap_or(443))
+                    .await?;
+                if let Some(config_list) = &config_list {
+                    validate_config_list(config_list)
+                        .map_err(|e| ErrorType::EchLookup(format!("{host}: {e}")))?;
+                }
+                Ok(config_list)
+            }
+        }
+    }
+}
+
+/// Result of ECH negotiation, in the extensions of every response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EchStatus {
+    /// The server accepted ECH, the inner ClientHello was used.
+    Accepted,
+    /// An ECHConfigList was offered, but the server did not accept it and the outer
+    /// ClientHello was used. The request is not failed for it.
+    Rejected,
+    /// No ECHConfigList was offered, only GREASE ECH is sent if the spec has one.
+    NotOffered,
+}
+
+/// Source of ECHConfigList, which is usually published in the HTTPS DNS record
+pub trait EchConfigResolver: fmt::Debug + Send + Sync {
+    /// ECHConfigList of the server, `None` if it does not support ECH.
+    ///
+    /// An error fails the request, instead of falling back to sending the real server
+    /// name in clear.
+    fn resolve<'a>(
+        &'a self,
+        host: &'a str,
+        port: u16,
+    ) -> BoxFuture<'a, Result<Option<Vec<u8>>, ErrorType>>;
+}
+
+/// Look up HTTPS records by DNS over HTTPS (RFC 8484), with answers cached by their TTL.
+#[derive(Debug)]
+pub struct DohResolver {
+    client: Client,
+    endpoint: Uri,
+    cache: Mutex<HashMap<(String, u16), CachedAnswer>>,
+}
+
+/// Expiry and ECHConfigList of a host
+type CachedAnswer = (Instant, Option<Vec<u8>>);
+
+impl DohResolver {
+    /// Query the endpoint like `https://cloudflare-dns.com/dns-query`, with a dedicated
+    /// client which does not use ECH itself.
+    pub fn new(endpoint: Uri) -> Result<Self, ErrorType> {
+        Ok(Self {
+            client: Client::new()?,
+            endpoint,
+            cache: Mutex::new(HashMap::new()),
+        })
+    }
+
+    async fn query(&self, host: &str, port: u16) -> Result<Option<Vec<u8>>, ErrorType> {
+        let key = (host.to_string(), port);
+        if let Some((expires, config_list)) = self.cache.lock().unwrap().get(&key) {
+            if *expires > Instant::now() {
+                return Ok(config_list.clone());
+            }
+        }
+
+        let dns_message = HeaderValue::from_static("application/dns-message");
+        let query =
+            dns_query(host, port).map_err(|e| ErrorType::EchLookup(format!("{host}: {e}")))?;
+        let mut request = Request::post(self.endpoint.clone()).set_body(query);
+        request
+            .headers
+            .insert(header::CONTENT_TYPE, dns_message.clone());
+        request.headers.insert(header::ACCEPT, dns_message);
+
+        let response = self.client.execute_buffered(request).await?;
+        if !response.status().is_success() {
+            return Err(ErrorType::EchLookup(format!(
+                "{host}: DoH server responded {}",
+                response.status()
+            )));
+        }
+        let (config_list, ttl) = parse_https_answer(response.body())
+            .map_err(|e| ErrorType::EchLookup(format!("{host}: {e}")))?;
+
+        let expires = Instant::now() + Duration::from_secs(ttl.into());
+        cache_answer(
+            &mut self.cache.lock().unwrap(),
+            key,
+            (expires, config_list.clone()),
+        );
+        Ok(config_list)
+    }
+}
+
+/// Cache the answer of a host, evicting expired ones so that the cache is bounded by the
+/// hosts looked up within a TTL
+fn cache_answer(
+    cache: &mut HashMap<(String, u16), CachedAnswer>,
+    key: (String, u16),
+    answer: CachedAnswer,
+) {
+    let now = Instant::now();
+    cache.retain(|_, (expires, _)| *expires > now);
+    cache.insert(key, answer);
+}
+
+impl EchConfigResolver for DohResolver {
+    fn resolve<'a>(
+        &'a self,
+        host: &'a str,
+        port: u16,
+    ) -> BoxFuture<'a, Result<Option<Vec<u8>>, ErrorType>> {
+        Box::pin(self.query(host, port))
+    }
+}
+
+/// Check the structure of ECHConfigList, at least one ECHConfig must be of a supported
+/// version
+fn validate_config_list(config_list: &[u8]) -> Result<(), String> {
+    let len = config_list
+        .get(..2)
+        .map(|l| u16::from_be_bytes([l[0], l[1]]) as usize)
+        .ok_or("ECHConfigList is truncated")?;
+    if config_list.len() != 2 + len {
+        return Err("length of ECHConfigList mismatches".to_string());
+    }
+
+    let mut configs = &config_list[2..];
+    let mut supported = false;
+    while !configs.is_empty() {
+        let (version, len) = match configs {
+            [v0, v1, l0, l1, ..] => (
+                u16::from_be_bytes([*v0, *v1]),
+                u16::from_be_bytes([*l0, *l1]) as usize,
+            ),
+            _ => return Err("ECHConfig is truncated".to_string()),
+        };
+        if configs.len() < 4 + len {
+            return Err("ECHConfig is truncated".to_string());
+        }
+        supported |= version == ECH_VERSION;
+        configs = &configs[4 + len..];
+    }
+    if !supported {
+        return Err(format!("no ECHConfig of version {ECH_VERSION:#06x}"));
+    }
+    Ok(())
+}
+
+/// DNS query of the HTTPS record, the name is prefixed by port if it's not 443
+fn dns_query(host: &str, port: u16) -> Result<Vec<u8>, String> {
+    if !host.is_ascii() {
+        return Err("host must be ASCII, e.g. punycode for IDN".to_string());
+    }
+    let name = match port {
+        443 => host.to_string(),
+        port => format!("_{port}._https.{host}"),
+    };
+
+    // id 0 as suggested by RFC 8484, recursion desired, 1 question
+    let mut query = vec![0, 0, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(format!("invalid DNS label {label:?}"));
+        }
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    query.extend_from_slice(&DNS_TYPE_HTTPS.to_be_bytes());
+    query.extend_from_slice(&1u16.to_be_bytes()); // IN
+    Ok(query)
+}
+
+/// ECHConfigList in the HTTPS record of highest priority, along with TTL of the answer
+fn parse_https_answer(message: &[u8]) -> Result<(Option<Vec<u8>>, u32), String> {
+    let truncated = || "DNS message is truncated".to_string();
+    let u16_at = |offset: usize| {
+        message
+            .get(offset..offset + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .ok_or_else(truncated)
+    };
+
+    let rcode = message.get(3).ok_or_else(truncated)? & 0x0f;
+    match rcode {
+        0 => {}
+        // NXDOMAIN
+        3 => return Ok((None, NEGATIVE_TTL)),
+        rcode => return Err(format!("DNS query failed with rcode {rcode}")),
+    }
+    let questions = u16_at(4)?;
+    let answers = u16_at(6)?;
+
+    let mut offset = 12;
+    for _ in 0..questions {
+        offset = skip_name(message, offset).ok_or_else(truncated)? + 4;
+    }
+
+    let mut best: Option<(u16, Vec<u8>)> = None;
+    let mut ttl = u32::MAX;
+    for _ in 0..answers {
+        offset = skip_name(message, offset).ok_or_else(truncated)?;
+        let rr_type = u16_at(offset)?;
+        let rr_ttl = message
+            .get(offset + 4..offset + 8)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+            .ok_or_else(truncated)?;
+        let rdata_len = u16_at(offset + 8)? as usize;
+        let rdata_start = offset + 10;
+        offset = rdata_start + rdata_len;
+        let rdata = message.get(rdata_start..offset).ok_or_else(truncated)?;
+        ttl = ttl.min(rr_ttl);
+        // e.g. CNAME records of the chain
+        if rr_type != DNS_TYPE_HTTPS {
+            continue;
+        }
+
+        // AliasMode records (priority 0) are not followed
+        let priority = rdata
+            .get(..2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .ok_or_else(truncated)?;
+        if priority == 0 {
+            continue;
+        }
+        if best.as_ref().is_some_and(|(p, _)| *p <= priority) {
+            continue;
+        }
+        // TargetName is never compressed
+        let mut param = skip_name(rdata, 2).ok_or_else(truncated)?;
+        while param < rdata.len() {
+            let header = rdata.get(param..param + 4).ok_or_else(truncated)?;
+            let key = u16::from_be_bytes([header[0], header[1]]);
+            let len = u16::from_be_bytes([header[2], header[3]]) as usize;
+            let value = rdata
+                .get(param + 4..param + 4 + len)
+                .ok_or_else(truncated)?;
+            if key == SVC_PARAM_ECH {
+                best = Some((priority, value.to_vec()));
+            }
+            param += 4 + len;
+        }
+    }
+
+    let ttl = if ttl == u32::MAX { NEGATIVE_TTL } else { ttl };
+    Ok((best.map(|(_, config_list)| config_list), ttl))
+}
+
+/// Offset after the domain name, which may end with a compression pointer
+fn skip_name(message: &[u8], mut offset: usize) -> Option<usize> {
+    loop {
+        let len = *message.get(offset)?;
+        match len {
+            0 => return Some(offset + 1),
+            len if len & 0xc0 == 0xc0 => return Some(offset + 2),
+            len => offset += 1 + len as usize,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::impersonate::profiles;
+
+    /// ECHConfigList with a single ECHConfig of unchecked contents
+    const CONFIG_LIST: [u8; 9] = [0x00, 0x07, 0xfe, 0x0d, 0x00, 0x03, 0x01, 0x02, 0x03];
+
+    #[test]
+    fn test_ech_mode_validate() {
+        let chrome = profiles::chrome(131);
+        let okhttp = profiles::okhttp(4);
+        let config = EchMode::Config(CONFIG_LIST.to_vec());
+        assert!(config.validate(Some(&chrome)).is_ok());
+        assert!(EchMode::Grease.validate(Some(&okhttp)).is_ok());
+        assert!(EchMode::Grease.validate(None).is_ok());
+        // pre-defined template, which may not carry the ECH extension
+        assert!(matches!(
+            config.validate(None),
+            Err(ErrorType::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            config.validate(Some(&okhttp)),
+            Err(ErrorType::InvalidConfig(_))
+        ));
+
+        for invalid in [
+            &[][..],
+            &[0x00, 0x08, 0xfe, 0x0d, 0x00, 0x03, 0x01, 0x02, 0x03],
+            &[0x00, 0x07, 0xfe, 0x0d, 0x00, 0x04, 0x01, 0x02, 0x03],
+            &[0x00, 0x07, 0xfe, 0x0a, 0x00, 0x03, 0x01, 0x02, 0x03],
+        ] {
+            assert!(
+                EchMode::Config(invalid.to_vec())
+                    .validate(Some(&chrome))
+                    .is_err(),
+                "{invalid:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_cache_answer() {
+        let now = Instant::now();
+        let mut cache = HashMap::new();
+        cache.insert(("expired.com".to_string(), 443), (now, None));
+        cache.insert(
+            ("fresh.com".to_string(), 443),
+            (now + Duration::from_secs(60), None),
+        );
+
+        cache_answer(
+            &mut cache,
+            ("example.com".to_string(), 443),
+            (now + Duration::from_secs(60), Some(CONFIG_LIST.to_vec())),
+        );
+        let mut hosts: Vec<_> = cache.keys().map(|(host, _)| host.as_str()).collect();
+        hosts.sort();
+        assert_eq!(hosts, ["example.com", "fresh.com"]);
+    }
+
+    #[test]
+    fn test_dns_query() {
+        let query = dns_query("example.com", 443).unwrap();
+        assert_eq!(&query[12..25], b"\x07example\x03com\x00");
+        assert_eq!(&query[25..], &[0, 65, 0, 1]);
+
+        let query = dns_query("example.com", 8443).unwrap();
+        assert_eq!(&query[12..25], b"\x05_8443\x06_https");
+
+        let long_label = format!("{}.com", "a".repeat(64));
+        for invalid in ["bücher.de", "a..com", long_label.as_str()] {
+            assert!(dns_query(invalid, 443).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_parse_https_answer() {
+        let mut message = vec![0, 0, 0x81, 0x80, 0, 1, 0, 3, 0, 0, 0, 0];
+        let question = dns_query("example.com", 443).unwrap();
+        message.extend_from_slice(&question[12..]);
+
+        let mut answer = |priority: u16, ttl: u32, params: &[(u16, &[u8])]| {
+            // compression pointer to the question
+            message.extend_from_slice(&[0xc0, 12, 0, 65, 0, 1]);
+            message.extend_from_slice(&ttl.to_be_bytes());
+            let mut rdata = priority.to_be_bytes().to_vec();
+            rdata.push(0); // TargetName "."
+            for (key, value) in params {
+                rdata.extend_from_slice(&key.to_be_bytes());
+                rdata.extend_from_slice(&(value.len() as u16).to_be_bytes());
+                rdata.extend_from_slice(value);
+            }
+            message.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+            message.extend_from_slice(&rdata);
+        };
+        answer(2, 300, &[(1, b"\x02h2"), (SVC_PARAM_ECH, &[0, 0])]);
+        answer(0, 600, &[]);
+        answer(1, 120, &[(1, b"\x02h3"), (SVC_PARAM_ECH, &CONFIG_LIST)]);
+
+        let (config_list, ttl) = parse_https_answer(&message).unwrap();
+        assert_eq!(config_list.as_deref(), Some(&CONFIG_LIST[..]));
+        assert_eq!(ttl, 120);
+
+        message[7] = 0;
+        assert_eq!(
+            parse_https_answer(&message[..29]).unwrap(),
+            (None, NEGATIVE_TTL)
+        );
+
+        message[3] = 0x83;
+        assert_eq!(parse_https_answer(&message).unwrap(), (None, NEGATIVE_TTL));
+        message[3] = 0x82;
+        assert!(parse_https_answer(&message).is_err());
+
+        // CNAME to the root, with rdata shorter than a priority
+        let mut message = vec![0, 0, 0x81, 0x80, 0, 1, 0, 1, 0, 0, 0, 0];
+        message.extend_from_slice(&question[12..]);
+        message.extend_from_slice(&[0xc0, 12, 0, 5, 0, 1, 0, 0, 0, 30, 0, 1, 0]);
+        assert_eq!(parse_https_answer(&message).unwrap(), (None, 30));
+    }
+}
@@ -1,20 +1,46 @@
+mod akamai;
+mod client_hello;
+mod fingerprint;
+mod grease;
+mod http2;
+mod ja3;
+pub mod pcap;
+pub mod profiles;
+mod request_kind;
+#[cfg(feature = "serde")]
+mod serde_support;
+mod validate;
+
+pub use client_hello::ClientHello;
+pub use grease::{GreasePolicy, GreaseSlot, GreaseValues};
+pub use http2::{
+    ExtensiblePriority, Http2PriorityFrame, Http2Setting, Http2SettingId, Http2Settings,
+    Http2SettingsBuilder,
+};
+pub use ja3::Ja3Options;
+pub use request_kind::RequestKind;
+pub use validate::{Severity, SpecDiagnostic};
+
+use std::collections::BTreeMap;
+
 use http::HeaderMap;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 
 use crate::ffi::{
     ClientHelloIdFfi, ClientHelloSpecFfi, HttpHeaderFfi, ImpersonationConfigFfi, TlsExtensionFfi,
     UTlsConfigFfi,
 };
 
-pub use crate::ffi::{
-    Http2PriorityParamFfi as Http2PriorityParam, Http2SettingFfi as Http2Setting,
-};
+pub use crate::ffi::Http2PriorityParamFfi as Http2PriorityParam;
 
-#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
 pub struct ImpersonationConfig {
     /// TLS Fingerprint Impersonation
     pub utls_config: UTlsConfig,
     /// HTTP2 Fingerprint Impersonation
-    pub http2_settings_frame: Vec<Http2Setting>,
+    pub http2_settings_frame: Http2Settings,
     /// HTTP2 Connection flow
     pub http2_connection_flow: u32,
     /// Common Pseudo Header Order
@@ -22,15 +48,30 @@ pub struct ImpersonationConfig {
     /// Common Header Order
     pub common_header_order: Vec<String>,
     /// Brower's common headers
+    #[cfg_attr(feature = "serde", serde(with = "serde_support::header_map"))]
     pub common_headers: HeaderMap,
     /// HTTP2 Header Priority, for HTTP2 fingerprint fmpersonation
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "serde_support::Http2PriorityParamDef")
+    )]
     pub http2_header_priority: Http2PriorityParam,
+    /// HTTP2 PRIORITY frames sent on each new connection, like Firefox's tree of idle streams
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub http2_priority_frames: Vec<Http2PriorityFrame>,
+    /// Headers replacing the kind dependent ones of `common_headers` for each
+    /// [`RequestKind`], see [`RequestKind::HEADERS`]. Only used by Rust side.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, with = "serde_support::kind_headers")
+    )]
+    pub request_kind_headers: BTreeMap<RequestKind, HeaderMap>,
 }
 
 impl From<ImpersonationConfig> for ImpersonationConfigFfi {
     fn from(value: ImpersonationConfig) -> Self {
         let mut common_headers = Vec::with_capacity(value.common_headers.len());
-        value.common_headers.keys().into_iter().for_each(|key| {
+        value.common_headers.keys().for_each(|key| {
             let v = value
                 .common_headers
                 .get_all(key)
@@ -44,17 +85,23 @@ impl From<ImpersonationConfig> for ImpersonationConfigFfi {
         });
         Self {
             utls_config: value.utls_config.into(),
-            http2_settings_frame: value.http2_settings_frame,
+            http2_settings_frame: value.http2_settings_frame.into_ffi(),
             http2_connection_flow: value.http2_connection_flow,
             common_pseudo_header_order: value.common_pseudo_header_order,
             common_header_order: value.common_header_order,
             common_headers,
             http2_header_priority: value.http2_header_priority,
+            http2_priority_frames: value
+                .http2_priority_frames
+                .into_iter()
+                .map(Into::into)
+                .collect(),
         }
     }
 }
 
-#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
 pub struct UTlsConfig {
     pub id: ClientHelloId,
     pub spec: Option<ClientHelloSpec>,
@@ -72,14 +119,15 @@ impl From<UTlsConfig> for UTlsConfigFfi {
     }
 }
 
-#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
 pub enum ClientHelloId {
-    Firefox(&'static str),
-    Chrome(&'static str),
-    IOS(&'static str),
-    Android11Okhttp(&'static str),
-    Edge(&'static str),
-    Safari(&'static str),
+    Firefox(String),
+    Chrome(String),
+    IOS(String),
+    Android11Okhttp(String),
+    Edge(String),
+    Safari(String),
     Custom,
 }
 
@@ -97,20 +145,26 @@ impl ClientHelloId {
         }
     }
     #[inline]
-    pub fn version(&self) -> &'static str {
+    pub fn version(&self) -> &str {
         match self {
-            Self::Firefox(v) => v,
-            Self::Chrome(v) => v,
-            Self::IOS(v) => v,
-            Self::Android11Okhttp(v) => v,
-            Self::Edge(v) => v,
-            Self::Safari(v) => v,
+            Self::Firefox(v)
+            | Self::Chrome(v)
+            | Self::IOS(v)
+            | Self::Android11Okhttp(v)
+            | Self::Edge(v)
+            | Self::Safari(v) => v,
             Self::Custom => "0",
         }
     }
+    #[inline]
+    pub fn is_custom(&self) -> bool {
+        matches!(self, Self::Custom)
+    }
 }
 
-#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Debug, Clone)]
 pub struct ClientHelloSpec {
     cipher_suites: Vec<u16>,
     /// default compressionNone, vec![0]
@@ -118,6 +172,36 @@ pub struct ClientHelloSpec {
     extensions: Vec<TlsExtension>,
     tls_version_min: TlsVersion,
     tls_version_max: TlsVersion,
+    shuffle: ShuffleMode,
+    grease: GreasePolicy,
+}
+
+/// How the extensions are shuffled to avoid ossification, introduced by **Chrome 106**.
+///
+/// GREASE, padding and pre_shared_key extensions always stay in place, along with the
+/// types listed in `fixed`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ShuffleMode {
+    /// Extensions are sent in the given order. Default one.
+    #[default]
+    Disabled,
+    /// Shuffle once when the extensions are set, reproducible with the same seed across
+    /// platforms and versions of this crate.
+    Seeded { seed: u64, fixed: Vec<u16> },
+    /// Shuffle by Go side for each new TLS connection, like Chrome does.
+    PerConnection { fixed: Vec<u16> },
+}
+
+impl ShuffleMode {
+    /// Extension types kept in place besides the builtin ones
+    #[inline]
+    fn fixed(&self) -> &[u16] {
+        match self {
+            Self::Disabled => &[],
+            Self::Seeded { fixed, .. } | Self::PerConnection { fixed } => fixed,
+        }
+    }
 }
 
 impl Default for ClientHelloSpec {
@@ -128,6 +212,8 @@ impl Default for ClientHelloSpec {
             extensions: Vec::with_capacity(32),
             tls_version_min: TlsVersion::VersionTLS12,
             tls_version_max: TlsVersion::VersionTLS13,
+            shuffle: ShuffleMode::Disabled,
+            grease: GreasePolicy::Chrome,
         }
     }
 }
@@ -178,22 +264,44 @@ impl ClientHelloSpec {
     /// Set tls extensions
     ///
     /// # Params
-    /// - `enable_shuffle`: enable shuffle extensions.
-    ///
-    ///    ShuffleChromeTLSExtensions shuffles the extensions in the ClientHelloSpec to avoid ossification.
-    ///    It shuffles every extension except GREASE, padding and pre_shared_key extensions.
-    ///
-    ///    This feature was first introduced by **Chrome 106**.
+    /// - `enable_shuffle`: shuffle the extensions once with a random seed, see
+    ///   [`ShuffleMode`] for reproducible or per-connection shuffling.
     /// - `extensions`: tls extensions
-    pub fn set_extensions(mut self, enable_shuffle: bool, extensions: Vec<TlsExtension>) -> Self {
-        self.extensions = if enable_shuffle {
-            Self::shuffle_chrome_tls_extensions(extensions)
+    pub fn set_extensions(self, enable_shuffle: bool, extensions: Vec<TlsExtension>) -> Self {
+        let shuffle = if enable_shuffle {
+            ShuffleMode::Seeded {
+                seed: rand::random(),
+                fixed: Vec::new(),
+            }
         } else {
-            extensions
+            ShuffleMode::Disabled
         };
+        self.set_extensions_with_shuffle(extensions, shuffle)
+    }
+
+    /// Set tls extensions, shuffled by the given mode
+    pub fn set_extensions_with_shuffle(
+        mut self,
+        mut extensions: Vec<TlsExtension>,
+        shuffle: ShuffleMode,
+    ) -> Self {
+        if let ShuffleMode::Seeded { seed, fixed } = &shuffle {
+            shuffle_extensions(
+                &mut extensions,
+                fixed,
+                &mut ChaCha8Rng::seed_from_u64(*seed),
+            );
+        }
+        self.extensions = extensions;
+        self.shuffle = shuffle;
         self
     }
 
+    #[inline]
+    pub fn shuffle_mode(&self) -> &ShuffleMode {
+        &self.shuffle
+    }
+
     #[inline]
     pub fn set_tls_version_min(mut self, tls_version_min: TlsVersion) -> Self {
         self.tls_version_min = tls_version_min;
@@ -205,56 +313,53 @@ impl ClientHelloSpec {
         self.tls_version_max = tls_version_max;
         self
     }
+}
 
-    fn shuffle_chrome_tls_extensions(mut exts: Vec<TlsExtension>) -> Vec<TlsExtension> {
-        use rand::Rng;
-
-        // `skip_shuf` checks if the `exts[idx]` is a GREASE/padding/pre_shared_key extension,
-        // and returns true on success. For these extensions are considered positionally invariant.
-        let skip_shuf = |exts: &Vec<TlsExtension>, idx: usize| -> bool {
-            match &exts[idx] {
-                TlsExtension::TLSGrease => true,
-                TlsExtension::UtlsExtensionPadding => true,
-                TlsExtension::FakeExtensionPreSharedKey => true,
-                _ => false,
-            }
-        };
-
-        let swap = |i: usize, j: usize, exts: &mut Vec<TlsExtension>| {
-            if skip_shuf(&exts, i) || skip_shuf(&exts, j) {
-                return;
-            }
+/// Shuffle like utls's ShuffleChromeTLSExtensions, GREASE, padding, pre_shared_key and
+/// the `fixed` extensions are considered positionally invariant.
+fn shuffle_extensions(exts: &mut [TlsExtension], fixed: &[u16], rng: &mut impl Rng) {
+    let is_fixed = |ext: &TlsExtension| {
+        let ext_type = ext.ext_type();
+        is_grease(ext_type) || ext_type == 21 || ext_type == 41 || fixed.contains(&ext_type)
+    };
+
+    for i in (1..exts.len()).rev() {
+        // invariant: elements with index > i have been locked in place.
+        // sampled as u32 to draw the same values on 32 and 64 bit platforms
+        let j = rng.gen_range(0..=i as u32) as usize;
+        if !is_fixed(&exts[i]) && !is_fixed(&exts[j]) {
             exts.swap(i, j);
-        };
-
-        let mut rng = rand::thread_rng();
-        for i in (1..exts.len()).rev() {
-            // invariant: elements with index > i have been locked in place.
-            let j = rng.gen_range(0..(i + 1));
-
-            swap(i, j, &mut exts);
         }
-
-        exts
     }
 }
 
 impl From<ClientHelloSpec> for ClientHelloSpecFfi {
     fn from(value: ClientHelloSpec) -> Self {
+        let (grease_policy, grease_value) = value.grease.to_ffi();
         Self {
             cipher_suites: value.cipher_suites,
             compression_methods: value.compression_methods,
             extensions: value.extensions.into_iter().map(|ext| ext.into()).collect(),
             tls_version_min: value.tls_version_min as u16,
             tls_version_max: value.tls_version_max as u16,
+            shuffle_per_connection: matches!(value.shuffle, ShuffleMode::PerConnection { .. }),
+            shuffle_fixed_extensions: value.shuffle.fixed().to_vec(),
+            grease_policy,
+            grease_value,
         }
     }
 }
 
-// based on spec's GreaseStyle, GREASE_PLACEHOLDER may be replaced by another GREASE value
+// GREASE_PLACEHOLDER is replaced by the value of its slot, picked by spec's GreasePolicy
 // https://tools.ietf.org/html/draft-ietf-tls-grease-01
 pub static GREASE_PLACEHOLDER: u16 = 0x0a0a;
 
+/// Check if the value is one of the reserved GREASE values, `0x?a?a` with same bytes
+#[inline]
+pub fn is_grease(value: u16) -> bool {
+    value & 0x0f0f == 0x0a0a && value >> 8 == value & 0xff
+}
+
 // A list of cipher suite IDs that are, or have been, implemented by this
 // package.
 //
@@ -299,7 +404,8 @@ pub static TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305: u16 = TLS_ECDHE_RSA_WITH_CHACHA
 pub static TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305: u16 =
     TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256;
 
-#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
 #[repr(u16)]
 pub enum TlsExtension {
     ServerName = 0,
@@ -307,7 +413,7 @@ pub enum TlsExtension {
     SupportedCurves(Vec<CurveID>) = 10, // supported_groups in TLS 1.3, see RFC 8446, Section 4.2.7
     SupportedPoints(Vec<u8>) = 11,
     SignatureAlgorithms(Vec<SignatureScheme>) = 13,
-    ALPN(Vec<&'static str>) = 16,
+    ALPN(Vec<String>) = 16,
     StatusRequestV2 = 17,
     SCT = 18,
     ExtendedMasterSecret = 23,
@@ -323,7 +429,7 @@ pub enum TlsExtension {
     UtlsExtensionPadding = 21, // do not support customize its content
     UtlsExtensionCompressCertificate(Vec<u16>) = 27, // https://datatracker.ietf.org/doc/html/rfc8879#section-7.1
     UtlsExtensionApplicationSettings(Vec<String>) = 17513, // not IANA assigned
-    UtlsExtensionECH = 0xfe0d, // do not support customize its content, draft-ietf-tls-esni-17
+    UtlsExtensionECH = 0xfe0d, // GREASE, or real ECH with `EchMode`, draft-ietf-tls-esni-17
     UtlsExtensionECHOuterExtensions = 0xfd00, // do not support customize its content, draft-ietf-tls-esni-17
 
     // extensions with 'fake' prefix break connection, if server echoes them back
@@ -339,12 +445,48 @@ pub enum TlsExtension {
 
     // === Custom One ===
     Custom(u16) = 0xFFFF,
-    //**  === do not support ===
-    // EarlyData = 42,
-    // Cookie = 44,
-    // CertificateAuthorities = 47,
-    // UtlsFakeExtensionCustom = 1234,        // not IANA assigned, for ALPS
-    //**
+    /// Extension written to the wire byte-for-byte, for the ones not listed here, e.g.
+    /// EarlyData (42), Cookie (44), CertificateAuthorities (47) or record_size_limit (28).
+    Raw {
+        ext_type: u16,
+        data: Vec<u8>,
+    } = 0xFFFE,
+}
+
+impl TlsExtension {
+    /// IANA assigned extension type, [`GREASE_PLACEHOLDER`] for GREASE
+    pub fn ext_type(&self) -> u16 {
+        match self {
+            Self::ServerName => 0,
+            Self::StatusRequest => 5,
+            Self::SupportedCurves(_) => 10,
+            Self::SupportedPoints(_) => 11,
+            Self::SignatureAlgorithms(_) => 13,
+            Self::ALPN(_) => 16,
+            Self::StatusRequestV2 => 17,
+            Self::SCT => 18,
+            Self::ExtendedMasterSecret => 23,
+            Self::SessionTicket => 35,
+            Self::SupportedVersions(_) => 43,
+            Self::PSKModes(_) => 45,
+            Self::SignatureAlgorithmsCert(_) => 50,
+            Self::KeyShare(_) => 51,
+            Self::QUICTransportParameters => 57,
+            Self::RenegotiationInfo(..) => 0xff01,
+            Self::UtlsExtensionPadding => 21,
+            Self::UtlsExtensionCompressCertificate(_) => 27,
+            Self::UtlsExtensionApplicationSettings(_) => 17513,
+            Self::UtlsExtensionECH => 0xfe0d,
+            Self::UtlsExtensionECHOuterExtensions => 0xfd00,
+            Self::FakeExtensionTokenBinding(..) => 24,
+            Self::FakeExtensionDelegatedCredentials(_) => 34,
+            Self::FakeExtensionPreSharedKey => 41,
+            Self::FakeOldExtensionChannelID(_) => 30031,
+            Self::FakeExtensionChannelID(_) => 30032,
+            Self::TLSGrease => GREASE_PLACEHOLDER,
+            Self::Custom(ext_type) | Self::Raw { ext_type, .. } => *ext_type,
+        }
+    }
 }
 
 impl From<TlsExtension> for TlsExtensionFfi {
@@ -360,16 +502,7 @@ impl From<TlsExtension> for TlsExtensionFfi {
             },
             TlsExtension::SupportedCurves(curves) => Self {
                 ext_type: 10,
-                vec_u16: curves
-                    .into_iter()
-                    .map(|curve| match curve {
-                        CurveID::CurveP256 => 23,
-                        CurveID::CurveP384 => 24,
-                        CurveID::CurveP521 => 25,
-                        CurveID::X25519 => 29,
-                        CurveID::Custom(id) => id,
-                    })
-                    .collect(),
+                vec_u16: curves.iter().map(CurveID::id).collect(),
                 ..Default::default()
             },
             TlsExtension::SupportedPoints(points) => Self {
@@ -384,7 +517,7 @@ impl From<TlsExtension> for TlsExtensionFfi {
             },
             TlsExtension::ALPN(protocols) => Self {
                 ext_type: 16,
-                vec_string: protocols.iter().map(|&s| s.to_string()).collect(),
+                vec_string: protocols,
                 ..Default::default()
             },
             TlsExtension::StatusRequestV2 => Self {
@@ -424,14 +557,7 @@ impl From<TlsExtension> for TlsExtensionFfi {
                 let mut vec_usize = Vec::with_capacity(shares.len() * 3);
 
                 shares.into_iter().for_each(|(group, keys)| {
-                    let group = match group {
-                        CurveID::CurveP256 => 23,
-                        CurveID::CurveP384 => 24,
-                        CurveID::CurveP521 => 25,
-                        CurveID::X25519 => 29,
-                        CurveID::Custom(id) => id,
-                    };
-                    vec_u16.push(group);
+                    vec_u16.push(group.id());
                     vec_usize.push(keys.len());
                     vec_u8.extend(keys);
                 });
@@ -518,21 +644,58 @@ impl From<TlsExtension> for TlsExtensionFfi {
                 ext_type,
                 ..Default::default()
             },
+            TlsExtension::Raw { ext_type, data } => Self {
+                ext_type,
+                vec_u8: data,
+                is_raw: true,
+                ..Default::default()
+            },
         }
     }
 }
 
-#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
 #[repr(u16)]
 pub enum CurveID {
     CurveP256 = 23,
     CurveP384 = 24,
     CurveP521 = 25,
     X25519 = 29,
+    /// GREASE group, see [`GreaseSlot::Group`]
+    Grease = 0x0a0a,
     Custom(u16) = 0xFFFF,
 }
 
-#[derive(Debug, Copy, Clone)]
+impl CurveID {
+    /// IANA assigned id of the group
+    #[inline]
+    pub fn id(&self) -> u16 {
+        match self {
+            Self::CurveP256 => 23,
+            Self::CurveP384 => 24,
+            Self::CurveP521 => 25,
+            Self::X25519 => 29,
+            Self::Grease => GREASE_PLACEHOLDER,
+            Self::Custom(id) => *id,
+        }
+    }
+
+    /// Group of the IANA assigned id, GREASE values are [`CurveID::Grease`]
+    pub fn from_id(id: u16) -> Self {
+        match id {
+            23 => Self::CurveP256,
+            24 => Self::CurveP384,
+            25 => Self::CurveP521,
+            29 => Self::X25519,
+            id if is_grease(id) => Self::Grease,
+            id => Self::Custom(id),
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Copy, Clone, num_enum::TryFromPrimitive)]
 #[repr(u16)]
 pub enum SignatureScheme {
     // RSASSA-PKCS1-v1_5 algorithms.
@@ -558,7 +721,8 @@ pub enum SignatureScheme {
     ECDSAWithSHA1 = 0x0203,
 }
 
-#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, num_enum::TryFromPrimitive)]
 #[repr(u16)]
 pub enum TlsVersion {
     VersionTLS10 = 0x0301,
@@ -570,3 +734,55 @@ pub enum TlsVersion {
     // supported by this package. See golang.org/issue/32716.
     VersionSSL30 = 0x0300,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shuffle_mode() {
+        let extensions = profiles::chrome(131).utls_config.spec.unwrap().extensions;
+        let shuffled = |seed: u64, fixed: Vec<u16>| {
+            ClientHelloSpec::new()
+                .set_extensions_with_shuffle(
+                    extensions.clone(),
+                    ShuffleMode::Seeded { seed, fixed },
+                )
+                .extensions
+                .iter()
+                .map(TlsExtension::ext_type)
+                .collect::<Vec<_>>()
+        };
+        let original: Vec<u16> = extensions.iter().map(TlsExtension::ext_type).collect();
+
+        assert_eq!(shuffled(42, vec![]), shuffled(42, vec![]));
+        // pinned so that a change of the PRNG or the algorithm is noticed
+        assert_eq!(
+            shuffled(42, vec![]),
+            [
+                2570, 27, 45, 65037, 11, 18, 43, 35, 13, 0, 16, 65281, 5, 10, 51, 17513, 23, 2570,
+                21
+            ]
+        );
+        assert_ne!(shuffled(42, vec![]), original);
+        for seed in 0..16 {
+            let order = shuffled(seed, vec![0, 16]);
+            for (idx, &ext_type) in original.iter().enumerate() {
+                if is_grease(ext_type) || [0, 16, 21].contains(&ext_type) {
+                    assert_eq!(order[idx], ext_type, "seed {seed}");
+                }
+            }
+        }
+
+        // shuffled by Go side, the order is kept as is
+        let spec = profiles::chrome(131).utls_config.spec.unwrap();
+        assert_eq!(
+            spec.shuffle_mode(),
+            &ShuffleMode::PerConnection { fixed: vec![] }
+        );
+        assert_eq!(
+            spec.ja3(),
+            profiles::chrome(131).utls_config.spec.unwrap().ja3()
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use super::*;
+use crate::error::fingerprint_error::FingerprintError;
+
+/// Pseudo headers and their abbreviations in Akamai fingerprint
+const PSEUDO_HEADERS: [(&str, &str); 4] = [
+    (":method", "m"),
+    (":authority", "a"),
+    (":scheme", "s"),
+    (":path", "p"),
+];
+
+impl ImpersonationConfig {
+    /// Akamai HTTP2 fingerprint of this config, like
+    /// `1:65536;2:0;4:6291456;6:262144|15663105|0|m,a,s,p`.
+    ///
+    /// It's `SETTINGS|WINDOW_UPDATE|PRIORITY|PSEUDO_HEADER_ORDER`, where `00` means no
+    /// WINDOW_UPDATE frame and `0` means no PRIORITY frame. Each PRIORITY frame is written as
+    /// `stream_id:exclusive:stream_dep:weight`, with the real weight instead of the wire one.
+    pub fn akamai_fingerprint(&self) -> String {
+        let settings = self
+            .http2_settings_frame
+            .iter()
+            .map(|s| format!("{}:{}", u16::from(s.id), s.value))
+            .collect::<Vec<_>>()
+            .join(";");
+
+        let window_update = match self.http2_connection_flow {
+            0 => "00".to_string(),
+            flow => flow.to_string(),
+        };
+
+        let priority = if self.http2_priority_frames.is_empty() {
+            "0".to_string()
+        } else {
+            self.http2_priority_frames
+                .iter()
+                .map(|f| {
+                    format!(
+                        "{}:{}:{}:{}",
+                        f.stream_id,
+                        u8::from(f.priority.exclusive),
+                        f.priority.stream_dep,
+                        u16::from(f.priority.weight) + 1
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+
+        let pseudo_header_order = self
+            .common_pseudo_header_order
+            .iter()
+            .map(|h| {
+                PSEUDO_HEADERS
+                    .iter()
+                    .find(|(name, _)| name == h)
+                    .map_or(h.as_str(), |(_, abbr)| abbr)
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+
+        format!("{settings}|{window_update}|{priority}|{pseudo_header_order}")
+    }
+
+    /// Fill `http2_settings_frame`, `http2_connection_flow`, `http2_priority_frames` and
+    /// `common_pseudo_header_order` from an Akamai HTTP2 fingerprint, as reported by capture
+    /// tools.
+    ///
+    /// The fingerprint does not capture the priority of HEADERS frame, so
+    /// `http2_header_priority` is kept as is.
+    pub fn set_akamai_fingerprint(&mut self, fingerprint: &str) -> Result<(), FingerprintError> {
+        let invalid =
+            |reason: &str| FingerprintError::InvalidAkamai(format!("{reason}: {fingerprint}"));
+
+        let fields: Vec<&str> = fingerprint.trim().split('|').collect();
+        let [settings, window_update, priority, pseudo_header_order] = fields[..] else {
+            return Err(invalid("expect 4 fields"));
+        };
+
+        let mut builder = Http2Settings::builder();
+        if !settings.is_empty() {
+            for setting in settings.split(';') {
+                let (id, value) = setting
+                    .split_once(':')
+                    .and_then(|(id, value)| Some((id.parse::<u16>().ok()?, value.parse().ok()?)))
+                    .ok_or_else(|| invalid("invalid SETTINGS"))?;
+                builder = builder.setting(id, value);
+            }
+        }
+        let settings = builder.build().map_err(|e| invalid(&e.to_string()))?;
+
+        let connection_flow = match window_update {
+            "00" => 0,
+            flow => flow.parse().map_err(|_| invalid("invalid WINDOW_UPDATE"))?,
+        };
+
+        let priority_frames = if priority == "0" {
+            Vec::new()
+        } else {
+            priority
+                .split(',')
+                .map(|frame| {
+                    let parts = frame
+                        .split(':')
+                        .map(|p| p.parse::<u32>().ok())
+                        .collect::<Option<Vec<_>>>()?;
+                    let [stream_id, exclusive @ (0 | 1), stream_dep, weight @ 1..=256] = parts[..]
+                    else {
+                        return None;
+                    };
+                    let frame = Http2PriorityFrame::new(
+                        stream_id,
+                        stream_dep,
+                        exclusive == 1,
+                        (weight - 1) as u8,
+                    );
+                    frame.validate().is_ok().then_some(frame)
+                })
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| invalid("invalid PRIORITY"))?
+        };
+
+        let pseudo_header_order = if pseudo_header_order.is_empty() {
+            Vec::new()
+        } else {
+            pseudo_header_order
+                .split(',')
+                .map(|abbr| {
+                    PSEUDO_HEADERS
+                        .iter()
+                        .find(|(_, a)| *a == abbr)
+                        .map(|(name, _)| name.to_string())
+                })
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| invalid("invalid pseudo header order"))?
+        };
+
+        self.http2_settings_frame = settings;
+        self.http2_connection_flow = connection_flow;
+        self.http2_priority_frames = priority_frames;
+        self.common_pseudo_header_order = pseudo_header_order;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_akamai_fingerprint() {
+        assert_eq!(
+            profiles::chrome(131).akamai_fingerprint(),
+            "1:65536;2:0;4:6291456;6:262144|15663105|0|m,a,s,p"
+        );
+        assert_eq!(
+            profiles::safari(18).akamai_fingerprint(),
+            "2:0;3:100;4:2097152;8:1;9:1|10420225|0|m,s,a,p"
+        );
+
+        let mut config = profiles::chrome(131);
+        config
+            .set_akamai_fingerprint("1:65536;4:131072;5:16384|12517377|0|m,p,a,s")
+            .unwrap();
+        assert_eq!(
+            config.akamai_fingerprint(),
+            "1:65536;4:131072;5:16384|12517377|0|m,p,a,s"
+        );
+
+        let firefox = profiles::firefox(133).akamai_fingerprint();
+        assert_eq!(
+            firefox,
+            "1:65536;4:131072;5:16384|12517377|3:0:0:201,5:0:0:101,7:0:0:1,9:0:7:1,11:0:3:1,13:0:0:241|m,p,a,s"
+        );
+        config.set_akamai_fingerprint(&firefox).unwrap();
+        assert_eq!(config.http2_priority_frames.len(), 6);
+        assert_eq!(config.akamai_fingerprint(), firefox);
+
+        config.set_akamai_fingerprint("|00|0|").unwrap();
+        assert!(config.http2_priority_frames.is_empty());
+        assert!(config.http2_settings_frame.is_empty());
+        assert_eq!(config.http2_connection_flow, 0);
+        assert_eq!(config.akamai_fingerprint(), "|00|0|");
+    }
+
+    #[test]
+    fn test_set_akamai_fingerprint_error() {
+        let mut config = profiles::chrome(131);
+        for invalid in [
+            "1:65536|15663105|0",
+            "1=65536|15663105|0|m,a,s,p",
+            "1:65536|-1|0|m,a,s,p",
+            "1:65536|15663105|0|m,a,x,p",
+            "1:65536|15663105|3:0:0:257|m,a,s,p",
+            "1:65536|15663105|3:2:0:201|m,a,s,p",
+            "1:65536|15663105|3:0:3:201|m,a,s,p",
+            "1:65536|15663105|3:0:0|m,a,s,p",
+            "1:65536;1:4096|15663105|0|m,a,s,p",
+            "4:2147483648|15663105|0|m,a,s,p",
+        ] {
+            assert!(
+                matches!(
+                    config.set_akamai_fingerprint(invalid),
+                    Err(FingerprintError::InvalidAkamai(_))
+                ),
+                "{invalid} should be rejected"
+            );
+        }
+        // nothing is changed on error
+        assert_eq!(
+            config.akamai_fingerprint(),
+            "1:65536;2:0;4:6291456;6:262144|15663105|0|m,a,s,p"
+        );
+    }
+}
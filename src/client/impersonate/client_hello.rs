@@ -0,0 +1,469 @@
+use super::*;
+use crate::error::fingerprint_error::FingerprintError;
+
+/// ContentType of TLS record carrying handshake messages
+const RECORD_HANDSHAKE: u8 = 0x16;
+/// HandshakeType of ClientHello
+const HANDSHAKE_CLIENT_HELLO: u8 = 0x01;
+
+/// ClientHello decoded from the wire, e.g. captured from a real browser.
+#[derive(Debug, Clone)]
+pub struct ClientHello {
+    /// Spec to replay the ClientHello.
+    ///
+    /// GREASE values are replaced by [`GREASE_PLACEHOLDER`], and key shares other than
+    /// GREASE ones are left empty to be generated for each connection. Extensions that
+    /// can not be expressed by other variants are kept as [`TlsExtension::Raw`].
+    pub spec: ClientHelloSpec,
+    /// Host name in `server_name` extension
+    pub server_name: Option<String>,
+}
+
+impl ClientHello {
+    /// Decode a ClientHello, either with TLS record headers (as it's sent over TCP) or
+    /// just the handshake message.
+    pub fn parse(data: &[u8]) -> Result<Self, FingerprintError> {
+        let message = match handshake_message(data)? {
+            Some(message) => message,
+            None => return Err(invalid("truncated")),
+        };
+        let mut reader = Reader::new(&message[4..]);
+
+        let legacy_version = reader.u16()?;
+        reader.skip(32)?; // random
+        reader.vec8()?; // legacy_session_id
+        let cipher_suites = reader.vec16()?;
+        if cipher_suites.len() % 2 != 0 {
+            return Err(invalid("odd length of cipher suites"));
+        }
+        let cipher_suites = cipher_suites
+            .chunks(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]))
+            .map(|c| if is_grease(c) { GREASE_PLACEHOLDER } else { c })
+            .collect();
+        let compression_methods = reader.vec8()?.to_vec();
+
+        let mut server_name = None;
+        let mut extensions = Vec::new();
+        let mut supported_versions = None;
+        if !reader.is_empty() {
+            let mut ext_reader = Reader::new(reader.vec16()?);
+            while !ext_reader.is_empty() {
+                let ext_type = ext_reader.u16()?;
+                let data = ext_reader.vec16()?;
+
+                if ext_type == 0 {
+                    server_name = decode_server_name(data);
+                }
+                if ext_type == 43 {
+                    supported_versions = decode_u16_list(data, Reader::vec8);
+                }
+                extensions.push(decode_extension(ext_type, data).unwrap_or_else(|| {
+                    TlsExtension::Raw {
+                        ext_type,
+                        data: data.to_vec(),
+                    }
+                }));
+            }
+        }
+
+        let version = |v: u16| {
+            TlsVersion::try_from(v).map_err(|_| invalid(&format!("unknown TLS version {v:#06x}")))
+        };
+        let (tls_version_min, tls_version_max) = match supported_versions {
+            Some(versions) => {
+                let mut versions = versions.into_iter().filter(|&v| !is_grease(v));
+                let first = versions
+                    .next()
+                    .ok_or_else(|| invalid("empty supported_versions"))?;
+                let (min, max) =
+                    versions.fold((first, first), |(min, max), v| (min.min(v), max.max(v)));
+                (version(min)?, version(max)?)
+            }
+            None => (version(legacy_version)?, version(legacy_version)?),
+        };
+
+        Ok(Self {
+            spec: ClientHelloSpec::new()
+                .set_cipher_suites(cipher_suites)
+                .set_compression_method(compression_methods)
+                .set_extensions(false, extensions)
+                .set_tls_version_min(tls_version_min)
+                .set_tls_version_max(tls_version_max),
+            server_name,
+        })
+    }
+
+    /// Decode a hex stream, as copied from Wireshark. Whitespaces and colons are ignored.
+    pub fn from_hex(hex: &str) -> Result<Self, FingerprintError> {
+        let digits = hex
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != ':')
+            .map(|c| c.to_digit(16).map(|d| d as u8))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| invalid("invalid hex digit"))?;
+        if digits.len() % 2 != 0 {
+            return Err(invalid("odd number of hex digits"));
+        }
+        let data: Vec<u8> = digits.chunks(2).map(|d| d[0] << 4 | d[1]).collect();
+        Self::parse(&data)
+    }
+
+    /// Decode the first ClientHello in a pcap or pcapng capture, see
+    /// [`pcap::first_client_hello`](super::pcap::first_client_hello).
+    #[inline]
+    pub fn from_capture(capture: &[u8]) -> Result<Self, FingerprintError> {
+        Self::parse(&super::pcap::first_client_hello(capture)?)
+    }
+}
+
+impl ClientHelloSpec {
+    /// Build the spec from a captured ClientHello, see [`ClientHello::parse`].
+    #[inline]
+    pub fn from_client_hello_bytes(data: &[u8]) -> Result<Self, FingerprintError> {
+        ClientHello::parse(data).map(|hello| hello.spec)
+    }
+}
+
+/// Extract the ClientHello handshake message (with its 4-byte header), reassembling it
+/// from TLS records if needed. `None` if more data is needed.
+pub(super) fn handshake_message(data: &[u8]) -> Result<Option<Vec<u8>>, FingerprintError> {
+    let mut message = Vec::new();
+    if data.first() == Some(&RECORD_HANDSHAKE) {
+        let mut reader = Reader::new(data);
+        while !reader.is_empty() && !is_complete(&message) {
+            let Ok(header) = reader.bytes(5) else {
+                return Ok(None);
+            };
+            if header[0] != RECORD_HANDSHAKE {
+                return Err(invalid("not a handshake record"));
+            }
+            let len = u16::from_be_bytes([header[3], header[4]]) as usize;
+            let Ok(fragment) = reader.bytes(len) else {
+                return Ok(None);
+            };
+            message.extend_from_slice(fragment);
+        }
+    } else {
+        message.extend_from_slice(data);
+    }
+
+    if message
+        .first()
+        .is_some_and(|&t| t != HANDSHAKE_CLIENT_HELLO)
+    {
+        return Err(invalid("not a ClientHello"));
+    }
+    if !is_complete(&message) {
+        return Ok(None);
+    }
+    let len = handshake_len(&message);
+    message.truncate(4 + len);
+    Ok(Some(message))
+}
+
+#[inline]
+fn handshake_len(message: &[u8]) -> usize {
+    u32::from_be_bytes([0, message[1], message[2], message[3]]) as usize
+}
+
+#[inline]
+fn is_complete(message: &[u8]) -> bool {
+    message.len() >= 4 && message.len() >= 4 + handshake_len(message)
+}
+
+/// Decode the extension into [`TlsExtension`], `None` if it can not be expressed so.
+fn decode_extension(ext_type: u16, data: &[u8]) -> Option<TlsExtension> {
+    let signature_schemes = |data: &[u8]| -> Option<Vec<SignatureScheme>> {
+        decode_u16_list(data, Reader::vec16)?
+            .into_iter()
+            .map(|s| SignatureScheme::try_from(s).ok())
+            .collect()
+    };
+
+    let ext = match ext_type {
+        ext_type if is_grease(ext_type) => TlsExtension::TLSGrease,
+        0 => TlsExtension::ServerName,
+        5 => TlsExtension::StatusRequest,
+        10 => TlsExtension::SupportedCurves(
+            decode_u16_list(data, Reader::vec16)?
+                .into_iter()
+                .map(CurveID::from_id)
+                .collect(),
+        ),
+        11 => TlsExtension::SupportedPoints(decode_all(data, |r| r.vec8().map(<[u8]>::to_vec))?),
+        13 => TlsExtension::SignatureAlgorithms(signature_schemes(data)?),
+        16 => TlsExtension::ALPN(decode_protocols(data)?),
+        17 => TlsExtension::StatusRequestV2,
+        18 => TlsExtension::SCT,
+        21 => TlsExtension::UtlsExtensionPadding,
+        23 => TlsExtension::ExtendedMasterSecret,
+        24 => decode_all(data, |r| {
+            let major = r.u8()?;
+            let minor = r.u8()?;
+            let parameters = r.vec8()?.to_vec();
+            Ok(TlsExtension::FakeExtensionTokenBinding(
+                major, minor, parameters,
+            ))
+        })?,
+        27 => TlsExtension::UtlsExtensionCompressCertificate(decode_u16_list(data, Reader::vec8)?),
+        34 => TlsExtension::FakeExtensionDelegatedCredentials(signature_schemes(data)?),
+        35 => TlsExtension::SessionTicket,
+        41 => TlsExtension::FakeExtensionPreSharedKey,
+        43 => TlsExtension::SupportedVersions(
+            decode_u16_list(data, Reader::vec8)?
+                .into_iter()
+                .map(|v| if is_grease(v) { GREASE_PLACEHOLDER } else { v })
+                .collect(),
+        ),
+        45 => TlsExtension::PSKModes(decode_all(data, |r| r.vec8().map(<[u8]>::to_vec))?),
+        50 => TlsExtension::SignatureAlgorithmsCert(signature_schemes(data)?),
+        51 => TlsExtension::KeyShare(decode_all(data, |r| {
+            let mut entries = Reader::new(r.vec16()?);
+            let mut shares = Vec::new();
+            while !entries.is_empty() {
+                let group = CurveID::from_id(entries.u16()?);
+                let key = entries.vec16()?;
+                // Only GREASE key share is kept, real ones are generated for each connection.
+                let key = if is_grease(group.id()) {
+                    key.to_vec()
+                } else {
+                    Vec::new()
+                };
+                shares.push((group, key));
+            }
+            Ok(shares)
+        })?),
+        57 => TlsExtension::QUICTransportParameters,
+        0xff01 => {
+            TlsExtension::RenegotiationInfo(1, decode_all(data, |r| r.vec8().map(<[u8]>::to_vec))?)
+        }
+        17513 => TlsExtension::UtlsExtensionApplicationSettings(decode_protocols(data)?),
+        0xfe0d => TlsExtension::UtlsExtensionECH,
+        0xfd00 => TlsExtension::UtlsExtensionECHOuterExtensions,
+        30031 => TlsExtension::FakeOldExtensionChannelID(true),
+        30032 => TlsExtension::FakeExtensionChannelID(true),
+        _ => return None,
+    };
+    Some(ext)
+}
+
+/// Decode the whole extension data, `None` if it's malformed
+fn decode_all<'a, T>(
+    data: &'a [u8],
+    f: impl FnOnce(&mut Reader<'a>) -> Result<T, FingerprintError>,
+) -> Option<T> {
+    let mut reader = Reader::new(data);
+    let value = f(&mut reader).ok()?;
+    reader.is_empty().then_some(value)
+}
+
+/// List of u16 prefixed by its length in bytes
+fn decode_u16_list<'a>(
+    data: &'a [u8],
+    vec: fn(&mut Reader<'a>) -> Result<&'a [u8], FingerprintError>,
+) -> Option<Vec<u16>> {
+    let list = decode_all(data, vec)?;
+    if list.len() % 2 != 0 {
+        return None;
+    }
+    Some(
+        list.chunks(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]))
+            .collect(),
+    )
+}
+
+/// ProtocolNameList of ALPN and ALPS
+fn decode_protocols(data: &[u8]) -> Option<Vec<String>> {
+    decode_all(data, |r| {
+        let mut list = Reader::new(r.vec16()?);
+        let mut protocols = Vec::new();
+        while !list.is_empty() {
+            let protocol = std::str::from_utf8(list.vec8()?)
+                .map_err(|_| invalid("protocol name is not UTF-8"))?;
+            protocols.push(protocol.to_string());
+        }
+        Ok(protocols)
+    })
+}
+
+/// First host_name in ServerNameList
+fn decode_server_name(data: &[u8]) -> Option<String> {
+    let mut list = Reader::new(decode_all(data, Reader::vec16)?);
+    while !list.is_empty() {
+        let name_type = list.u8().ok()?;
+        let name = list.vec16().ok()?;
+        if name_type == 0 {
+            return String::from_utf8(name.to_vec()).ok();
+        }
+    }
+    None
+}
+
+#[inline]
+fn invalid(reason: &str) -> FingerprintError {
+    FingerprintError::InvalidClientHello(reason.to_string())
+}
+
+/// Big-endian reader of TLS structures
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    #[inline]
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], FingerprintError> {
+        if self.data.len() < len {
+            return Err(invalid("unexpected end of data"));
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    #[inline]
+    fn skip(&mut self, len: usize) -> Result<(), FingerprintError> {
+        self.bytes(len).map(|_| ())
+    }
+
+    #[inline]
+    fn u8(&mut self) -> Result<u8, FingerprintError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    #[inline]
+    fn u16(&mut self) -> Result<u16, FingerprintError> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// Bytes prefixed by 1-byte length
+    #[inline]
+    fn vec8(&mut self) -> Result<&'a [u8], FingerprintError> {
+        let len = self.u8()? as usize;
+        self.bytes(len)
+    }
+
+    /// Bytes prefixed by 2-byte length
+    #[inline]
+    fn vec16(&mut self) -> Result<&'a [u8], FingerprintError> {
+        let len = self.u16()? as usize;
+        self.bytes(len)
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    /// ClientHello of OpenSSL 3.0 to `example.com`, with ALPN h2 and http/1.1
+    pub(in super::super) const OPENSSL_CLIENT_HELLO: &str = "1603010200010001fc03037ea4ef1694e99a70da146b689d1fab3deaefd996e502709ad5bffb978830460720dab4baf1b8a0b1b145d4cef156a31aebe19f0491bb28d6cb5038f811012f5ba80024130213031301c02cc030c02bc02fcca9cca8c024c028c023c027009f009e006b006700ff0100018f00000010000e00000b6578616d706c652e636f6d000b000403000102000a00160014001d0017001e0019001801000101010201030104002300000010000e000c02683208687474702f312e310016000000170000000d002a0028040305030603080708080809080a080b080408050806040105010601030303010302040205020602002b00050403040303002d00020101003300260024001d0020851795c8e46f1cf7969eb87a480d4205d1b39421015b91ee1989c2fe26530450001500d000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000";
+
+    pub(in super::super) fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_client_hello_from_hex() {
+        let hello = ClientHello::from_hex(OPENSSL_CLIENT_HELLO).unwrap();
+        let spaced: Vec<String> = OPENSSL_CLIENT_HELLO
+            .as_bytes()
+            .chunks(2)
+            .map(|c| String::from_utf8(c.to_vec()).unwrap())
+            .collect();
+        let spaced = ClientHello::from_hex(&spaced.join(":")).unwrap();
+        assert_eq!(spaced.spec.ja4(), hello.spec.ja4());
+
+        assert!(ClientHello::from_hex("16030").is_err());
+        assert!(ClientHello::from_hex("1603zz").is_err());
+    }
+
+    #[test]
+    fn test_parse_client_hello() {
+        let data = hex(OPENSSL_CLIENT_HELLO);
+        let hello = ClientHello::parse(&data).unwrap();
+        assert_eq!(hello.server_name.as_deref(), Some("example.com"));
+        assert_eq!(
+            hello.spec.ja3(),
+            "771,4866-4867-4865-49196-49200-49195-49199-52393-52392-49188-49192-49187-49191-159-158-107-103-255,0-11-10-35-16-22-23-13-43-45-51-21,29-23-30-25-24-256-257-258-259-260,0-1-2"
+        );
+        assert!(matches!(
+            hello.spec.tls_version_min,
+            TlsVersion::VersionTLS12
+        ));
+        assert!(matches!(
+            hello.spec.tls_version_max,
+            TlsVersion::VersionTLS13
+        ));
+
+        assert_eq!(hello.spec.ja4(), "t13d1812h2_85036bcba153_d41ae481755e");
+
+        // encrypt_then_mac, and signature_algorithms with schemes SignatureScheme lacks
+        let raw: Vec<(u16, usize)> = hello
+            .spec
+            .extensions
+            .iter()
+            .filter_map(|ext| match ext {
+                TlsExtension::Raw { ext_type, data } => Some((*ext_type, data.len())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(raw, vec![(22, 0), (13, 0x2a)]);
+
+        assert!(hello.spec.extensions.iter().any(|ext| matches!(
+            ext,
+            TlsExtension::KeyShare(shares) if shares.len() == 1 && shares[0].1.is_empty()
+        )));
+        assert!(hello.spec.extensions.iter().any(|ext| matches!(
+            ext,
+            TlsExtension::ALPN(protocols) if protocols == &["h2", "http/1.1"]
+        )));
+
+        // handshake message without record header
+        let spec = ClientHelloSpec::from_client_hello_bytes(&data[5..]).unwrap();
+        assert_eq!(spec.ja3(), hello.spec.ja3());
+
+        // split into two records
+        let message = &data[5..];
+        let mut records = Vec::new();
+        for fragment in [&message[..100], &message[100..]] {
+            records.extend_from_slice(&[0x16, 0x03, 0x01]);
+            records.extend_from_slice(&(fragment.len() as u16).to_be_bytes());
+            records.extend_from_slice(fragment);
+        }
+        let spec = ClientHelloSpec::from_client_hello_bytes(&records).unwrap();
+        assert_eq!(spec.ja3(), hello.spec.ja3());
+    }
+
+    #[test]
+    fn test_parse_client_hello_error() {
+        let data = hex(OPENSSL_CLIENT_HELLO);
+        for invalid in [
+            &data[..100],
+            &data[..3],
+            &[0x17, 0x03, 0x03, 0x00, 0x00],
+            &[0x02, 0, 0, 0],
+        ] {
+            assert!(
+                matches!(
+                    ClientHello::parse(invalid),
+                    Err(FingerprintError::InvalidClientHello(_))
+                ),
+                "{invalid:?} should be rejected"
+            );
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use md5::{Digest, Md5};
+use sha2::Sha256;
+
+use super::*;
+
+/// Placeholder of JA4 hash parts with empty input
+const JA4_EMPTY_HASH: &str = "000000000000";
+
+impl ClientHelloSpec {
+    /// JA3 string of the ClientHello produced by this spec, like
+    /// `771,4865-4866-4867,0-23-65281,29-23-24,0`.
+    ///
+    /// GREASE values are ignored, extensions are in the order of the spec. It's the
+    /// order after a [`ShuffleMode::Seeded`] shuffle, or before the one of
+    /// [`ShuffleMode::PerConnection`].
+    pub fn ja3(&self) -> String {
+        let join = |values: &mut dyn Iterator<Item = u16>| {
+            values
+                .filter(|&v| !is_grease(v))
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join("-")
+        };
+
+        let ciphers = join(&mut self.cipher_suites.iter().copied());
+        let extensions = join(&mut self.extensions.iter().map(TlsExtension::ext_type));
+        let curves = join(&mut self.supported_curves().map(CurveID::id));
+        let points = self
+            .extensions
+            .iter()
+            .find_map(|ext| match ext {
+                TlsExtension::SupportedPoints(points) => Some(points.as_slice()),
+                _ => None,
+            })
+            .unwrap_or_default()
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>()
+            .join("-");
+
+        format!(
+            "{},{ciphers},{extensions},{curves},{points}",
+            self.legacy_version()
+        )
+    }
+
+    /// MD5 of [`ja3`](Self::ja3) in lowercase hex, the form most threat-intel feeds use
+    pub fn ja3_hash(&self) -> String {
+        format!("{:x}", Md5::digest(self.ja3()))
+    }
+
+    /// JA4 fingerprint of the ClientHello produced by this spec over TCP, like
+    /// `t13d1516h2_8daaf6152771_02713d6af862`.
+    ///
+    /// GREASE values are ignored and the order of ciphers and extensions does not matter.
+    pub fn ja4(&self) -> String {
+        let version = self
+            .extensions
+            .iter()
+            .find_map(|ext| match ext {
+                TlsExtension::SupportedVersions(versions) => {
+                    versions.iter().copied().filter(|&v| !is_grease(v)).max()
+                }
+                _ => None,
+            })
+            .unwrap_or_else(|| self.legacy_version());
+        let version = match version {
+            0x0304 => "13",
+            0x0303 => "12",
+            0x0302 => "11",
+            0x0301 => "10",
+            0x0300 => "s3",
+            _ => "00",
+        };
+
+        let sni = if self
+            .extensions
+            .iter()
+            .any(|ext| matches!(ext, TlsExtension::ServerName))
+        {
+            'd'
+        } else {
+            'i'
+        };
+
+        let mut ciphers: Vec<u16> = self
+            .cipher_suites
+            .iter()
+            .copied()
+            .filter(|&c| !is_grease(c))
+            .collect();
+        let mut extensions: Vec<u16> = self
+            .extensions
+            .iter()
+            .map(TlsExtension::ext_type)
+            .filter(|&e| !is_grease(e))
+            .collect();
+
+        let alpn = self
+            .extensions
+            .iter()
+            .find_map(|ext| match ext {
+                TlsExtension::ALPN(protocols) => protocols.first(),
+                _ => None,
+            })
+            .map_or_else(|| "00".to_string(), |protocol| ja4_alpn(protocol));
+
+        let ja4_a = format!(
+            "t{version}{sni}{:02}{:02}{alpn}",
+            ciphers.len().min(99),
+            extensions.len().min(99)
+        );
+
+        ciphers.sort_unstable();
+        let ja4_b = ja4_hash(&hex_list(&ciphers), ciphers.is_empty());
+
+        // SNI and ALPN are already captured by the first part
+        extensions.retain(|&e| e != 0 && e != 16);
+        extensions.sort_unstable();
+        let mut ja4_c = hex_list(&extensions);
+        let signature_algorithms: Vec<u16> = self
+            .extensions
+            .iter()
+            .find_map(|ext| match ext {
+                TlsExtension::SignatureAlgorithms(schemes) => {
+                    Some(schemes.iter().map(|&s| s as u16).collect())
+                }
+                // e.g. decoded from a capture with schemes unknown to `SignatureScheme`
+                TlsExtension::Raw { ext_type: 13, data } => Some(
+                    data.get(2..)?
+                        .chunks_exact(2)
+                        .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                        .collect(),
+                ),
+                _ => None,
+            })
+            .unwrap_or_default();
+        if !signature_algorithms.is_empty() {
+            ja4_c.push('_');
+            ja4_c.push_str(&hex_list(&signature_algorithms));
+        }
+        let ja4_c = ja4_hash(&ja4_c, extensions.is_empty());
+
+        format!("{ja4_a}_{ja4_b}_{ja4_c}")
+    }
+
+    /// Version in the ClientHello itself, TLS 1.3 sends TLS 1.2 here
+    fn legacy_version(&self) -> u16 {
+        (self.tls_version_max.clone() as u16).min(TlsVersion::VersionTLS12 as u16)
+    }
+
+    pub(super) fn supported_curves(&self) -> impl Iterator<Item = &CurveID> {
+        self.extensions
+            .iter()
+            .find_map(|ext| match ext {
+                TlsExtension::SupportedCurves(curves) => Some(curves.iter()),
+                _ => None,
+            })
+            .into_iter()
+            .flatten()
+    }
+}
+
+/// First and last characters of the ALPN value, or of its hex form if not alphanumeric
+fn ja4_alpn(protocol: &str) -> String {
+    let bytes = protocol.as_bytes();
+    let (Some(&first), Some(&last)) = (bytes.first(), bytes.last()) else {
+        return "00".to_string();
+    };
+    if first.is_ascii_alphanumeric() && last.is_ascii_alphanumeric() {
+        format!("{}{}", first as char, last as char)
+    } else {
+        let first = format!("{first:02x}");
+        let last = format!("{last:02x}");
+        format!("{}{}", &first[..1], &last[1..])
+    }
+}
+
+/// Comma separated 4-digit lowercase hex values
+fn hex_list(values: &[u16]) -> String {
+    values
+        .iter()
+        .map(|v| format!("{v:04x}"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// First 12 hex digits of SHA256
+fn ja4_hash(input: &str, empty: bool) -> String {
+    if empty {
+        return JA4_EMPTY_HASH.to_string();
+    }
+    let mut hash = format!("{:x}", Sha256::digest(input));
+    hash.truncate(12);
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHROME_JA3: &str = "771,2570-4865-4866-4867-49195-49199-49196-49200-52393-52392-49171-49172-156-157-47-53,2570-0-23-65281-10-11-35-16-5-13-18-51-45-43-27-17513-65037-2570,2570-25497-29-23-24,0";
+
+    #[test]
+    fn test_ja3() {
+        let spec = ClientHelloSpec::from_ja3(CHROME_JA3).unwrap();
+        assert_eq!(
+            spec.ja3(),
+            "771,4865-4866-4867-49195-49199-49196-49200-52393-52392-49171-49172-156-157-47-53,0-23-65281-10-11-35-16-5-13-18-51-45-43-27-17513-65037,25497-29-23-24,0"
+        );
+        assert_eq!(spec.ja3_hash(), "305646c8f1c5975313f3801b619077db");
+    }
+
+    #[test]
+    fn test_ja4() {
+        let spec = ClientHelloSpec::from_ja3(CHROME_JA3).unwrap();
+        assert_eq!(spec.ja4(), "t13d1516h2_8daaf6152771_02713d6af862");
+
+        // order of extensions does not matter
+        let mut reversed = spec.clone();
+        reversed.extensions.reverse();
+        assert_ne!(spec.ja3(), reversed.ja3());
+        assert_eq!(spec.ja4(), reversed.ja4());
+
+        let spec = ClientHelloSpec::new()
+            .set_tls_version_max(TlsVersion::VersionTLS12)
+            .add_extension(TlsExtension::ALPN(vec!["http/1.1".to_string()]));
+        assert_eq!(spec.ja4(), "t12i0001h1_000000000000_000000000000");
+        assert_eq!(spec.ja3(), "771,,16,,");
+    }
+}
@@ -0,0 +1,146 @@
+use super::*;
+
+/// Where a GREASE value is used in the ClientHello, in the order of BoringSSL's slots
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GreaseSlot {
+    /// GREASE_PLACEHOLDER in cipher suites
+    CipherSuite,
+    /// [`CurveID::Grease`] in supported_groups and key_share
+    Group,
+    /// First [`TlsExtension::TLSGrease`]
+    Extension1,
+    /// Second [`TlsExtension::TLSGrease`], always different from the first one
+    Extension2,
+    /// GREASE_PLACEHOLDER in supported_versions
+    Version,
+}
+
+impl GreaseSlot {
+    pub const ALL: [Self; 5] = [
+        Self::CipherSuite,
+        Self::Group,
+        Self::Extension1,
+        Self::Extension2,
+        Self::Version,
+    ];
+}
+
+/// How GREASE values are picked for the slots of each TLS connection
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GreasePolicy {
+    /// The same value for every slot on every connection, must be a GREASE value.
+    ///
+    /// The second GREASE extension uses `value ^ 0x1010` to stay distinct, like BoringSSL.
+    Fixed(u16),
+    /// One random value per connection, shared by every slot.
+    RandomPerConnection,
+    /// An independent random value per slot and connection, like Chrome does. Default one.
+    #[default]
+    Chrome,
+}
+
+impl GreasePolicy {
+    /// Policy and the fixed value accepted by Go side
+    pub(crate) fn to_ffi(self) -> (u8, u16) {
+        match self {
+            Self::Fixed(value) => (0, value),
+            Self::RandomPerConnection => (1, 0),
+            Self::Chrome => (2, 0),
+        }
+    }
+}
+
+/// GREASE values used on the connection of a response, found in its extensions.
+///
+/// Slots not present in the ClientHelloSpec have no value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GreaseValues {
+    values: [Option<u16>; GreaseSlot::ALL.len()],
+}
+
+impl GreaseValues {
+    /// Value of each slot reported by Go side in the order of [`GreaseSlot::ALL`], 0 for
+    /// unused slots
+    pub(crate) fn from_ffi(values: &[u16]) -> Self {
+        let mut this = Self::default();
+        for (slot, &value) in this.values.iter_mut().zip(values) {
+            *slot = is_grease(value).then_some(value);
+        }
+        this
+    }
+
+    #[inline]
+    pub fn get(&self, slot: GreaseSlot) -> Option<u16> {
+        self.values[slot as usize]
+    }
+}
+
+impl ClientHelloSpec {
+    /// Set how GREASE values are picked, [`GreasePolicy::Chrome`] by default
+    #[inline]
+    pub fn set_grease_policy(mut self, policy: GreasePolicy) -> Self {
+        self.grease = policy;
+        self
+    }
+
+    #[inline]
+    pub fn grease_policy(&self) -> GreasePolicy {
+        self.grease
+    }
+
+    /// GREASE slots used by this spec
+    pub fn grease_slots(&self) -> Vec<GreaseSlot> {
+        let mut slots = Vec::new();
+        if self.cipher_suites.iter().any(|&c| is_grease(c)) {
+            slots.push(GreaseSlot::CipherSuite);
+        }
+        let has_grease_group = self.extensions.iter().any(|ext| match ext {
+            TlsExtension::SupportedCurves(curves) => curves.iter().any(|c| is_grease(c.id())),
+            TlsExtension::KeyShare(shares) => shares.iter().any(|(g, _)| is_grease(g.id())),
+            _ => false,
+        });
+        if has_grease_group {
+            slots.push(GreaseSlot::Group);
+        }
+        let grease_extensions = self
+            .extensions
+            .iter()
+            .filter(|ext| matches!(ext, TlsExtension::TLSGrease))
+            .count();
+        if grease_extensions >= 1 {
+            slots.push(GreaseSlot::Extension1);
+        }
+        if grease_extensions >= 2 {
+            slots.push(GreaseSlot::Extension2);
+        }
+        let has_grease_version = self.extensions.iter().any(|ext| {
+            matches!(ext, TlsExtension::SupportedVersions(versions) if versions.iter().any(|&v| is_grease(v)))
+        });
+        if has_grease_version {
+            slots.push(GreaseSlot::Version);
+        }
+        slots
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grease_slots() {
+        let spec = profiles::chrome(131).utls_config.spec.unwrap();
+        assert_eq!(spec.grease_slots(), GreaseSlot::ALL);
+        assert_eq!(spec.grease_policy(), GreasePolicy::Chrome);
+
+        let spec = profiles::firefox(133).utls_config.spec.unwrap();
+        assert!(spec.grease_slots().is_empty());
+
+        let values = GreaseValues::from_ffi(&[0x1a1a, 0x2a2a, 0xdada, 0xeaea, 0]);
+        assert_eq!(values.get(GreaseSlot::Group), Some(0x2a2a));
+        assert_eq!(values.get(GreaseSlot::Extension2), Some(0xeaea));
+        assert_eq!(values.get(GreaseSlot::Version), None);
+    }
+}
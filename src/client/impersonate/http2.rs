@@ -0,0 +1,418 @@
+use http::HeaderValue;
+
+use super::Http2PriorityParam;
+use crate::{
+    error::ErrorType,
+    ffi::{Http2PriorityFrameFfi, Http2SettingFfi},
+};
+
+/// Identifier of HTTP2 SETTINGS parameter, see RFC 9113, Section 6.5.2
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Http2SettingId {
+    /// SETTINGS_HEADER_TABLE_SIZE (0x1)
+    HeaderTableSize,
+    /// SETTINGS_ENABLE_PUSH (0x2), 0 or 1
+    EnablePush,
+    /// SETTINGS_MAX_CONCURRENT_STREAMS (0x3)
+    MaxConcurrentStreams,
+    /// SETTINGS_INITIAL_WINDOW_SIZE (0x4), at most 2^31-1
+    InitialWindowSize,
+    /// SETTINGS_MAX_FRAME_SIZE (0x5), between 2^14 and 2^24-1
+    MaxFrameSize,
+    /// SETTINGS_MAX_HEADER_LIST_SIZE (0x6)
+    MaxHeaderListSize,
+    /// SETTINGS_ENABLE_CONNECT_PROTOCOL (0x8), 0 or 1, see RFC 8441
+    EnableConnectProtocol,
+    /// SETTINGS_NO_RFC7540_PRIORITIES (0x9), 0 or 1, see RFC 9218
+    NoRfc7540Priorities,
+    /// Other ones, e.g. GREASE or unassigned ones sent by some clients
+    Unknown(u16),
+}
+
+impl Http2SettingId {
+    /// Range of the valid values
+    fn valid_range(self) -> std::ops::RangeInclusive<u32> {
+        match self {
+            Self::EnablePush | Self::EnableConnectProtocol | Self::NoRfc7540Priorities => 0..=1,
+            Self::InitialWindowSize => 0..=(1 << 31) - 1,
+            Self::MaxFrameSize => (1 << 14)..=(1 << 24) - 1,
+            _ => 0..=u32::MAX,
+        }
+    }
+}
+
+impl From<u16> for Http2SettingId {
+    fn from(value: u16) -> Self {
+        match value {
+            0x1 => Self::HeaderTableSize,
+            0x2 => Self::EnablePush,
+            0x3 => Self::MaxConcurrentStreams,
+            0x4 => Self::InitialWindowSize,
+            0x5 => Self::MaxFrameSize,
+            0x6 => Self::MaxHeaderListSize,
+            0x8 => Self::EnableConnectProtocol,
+            0x9 => Self::NoRfc7540Priorities,
+            id => Self::Unknown(id),
+        }
+    }
+}
+
+impl From<Http2SettingId> for u16 {
+    fn from(value: Http2SettingId) -> Self {
+        match value {
+            Http2SettingId::HeaderTableSize => 0x1,
+            Http2SettingId::EnablePush => 0x2,
+            Http2SettingId::MaxConcurrentStreams => 0x3,
+            Http2SettingId::InitialWindowSize => 0x4,
+            Http2SettingId::MaxFrameSize => 0x5,
+            Http2SettingId::MaxHeaderListSize => 0x6,
+            Http2SettingId::EnableConnectProtocol => 0x8,
+            Http2SettingId::NoRfc7540Priorities => 0x9,
+            Http2SettingId::Unknown(id) => id,
+        }
+    }
+}
+
+/// One parameter of HTTP2 SETTINGS frame
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Http2Setting {
+    pub id: Http2SettingId,
+    pub value: u32,
+}
+
+impl From<Http2Setting> for Http2SettingFfi {
+    fn from(value: Http2Setting) -> Self {
+        Self {
+            setting_id: value.id.into(),
+            setting_val: value.value,
+        }
+    }
+}
+
+/// Checked HTTP2 SETTINGS frame, in the order of being sent.
+///
+/// Order is part of the fingerprint, use [`Http2Settings::builder`] to create one.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(try_from = "Vec<Http2Setting>", into = "Vec<Http2Setting>")
+)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Http2Settings {
+    settings: Vec<Http2Setting>,
+}
+
+impl Http2Settings {
+    #[inline]
+    pub fn builder() -> Http2SettingsBuilder {
+        Http2SettingsBuilder::default()
+    }
+
+    #[inline]
+    pub fn iter(&self) -> std::slice::Iter<'_, Http2Setting> {
+        self.settings.iter()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.settings.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.settings.is_empty()
+    }
+
+    /// Value of the given parameter if it's sent
+    pub fn get(&self, id: Http2SettingId) -> Option<u32> {
+        let id = u16::from(id);
+        self.settings
+            .iter()
+            .find(|s| u16::from(s.id) == id)
+            .map(|s| s.value)
+    }
+
+    pub(crate) fn into_ffi(self) -> Vec<Http2SettingFfi> {
+        self.settings.into_iter().map(Into::into).collect()
+    }
+}
+
+impl TryFrom<Vec<Http2Setting>> for Http2Settings {
+    type Error = ErrorType;
+
+    /// Check the settings like [`Http2SettingsBuilder::build`]
+    #[inline]
+    fn try_from(settings: Vec<Http2Setting>) -> Result<Self, Self::Error> {
+        Http2SettingsBuilder { settings }.build()
+    }
+}
+
+impl From<Http2Settings> for Vec<Http2Setting> {
+    #[inline]
+    fn from(value: Http2Settings) -> Self {
+        value.settings
+    }
+}
+
+impl<'a> IntoIterator for &'a Http2Settings {
+    type Item = &'a Http2Setting;
+    type IntoIter = std::slice::Iter<'a, Http2Setting>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Builder of [`Http2Settings`], parameters are sent in the order they are added.
+#[derive(Debug, Clone, Default)]
+pub struct Http2SettingsBuilder {
+    settings: Vec<Http2Setting>,
+}
+
+impl Http2SettingsBuilder {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a parameter by its id
+    #[inline]
+    pub fn setting(mut self, id: impl Into<Http2SettingId>, value: u32) -> Self {
+        self.settings.push(Http2Setting {
+            id: id.into(),
+            value,
+        });
+        self
+    }
+
+    #[inline]
+    pub fn header_table_size(self, value: u32) -> Self {
+        self.setting(Http2SettingId::HeaderTableSize, value)
+    }
+
+    #[inline]
+    pub fn enable_push(self, value: u32) -> Self {
+        self.setting(Http2SettingId::EnablePush, value)
+    }
+
+    #[inline]
+    pub fn max_concurrent_streams(self, value: u32) -> Self {
+        self.setting(Http2SettingId::MaxConcurrentStreams, value)
+    }
+
+    #[inline]
+    pub fn initial_window_size(self, value: u32) -> Self {
+        self.setting(Http2SettingId::InitialWindowSize, value)
+    }
+
+    #[inline]
+    pub fn max_frame_size(self, value: u32) -> Self {
+        self.setting(Http2SettingId::MaxFrameSize, value)
+    }
+
+    #[inline]
+    pub fn max_header_list_size(self, value: u32) -> Self {
+        self.setting(Http2SettingId::MaxHeaderListSize, value)
+    }
+
+    #[inline]
+    pub fn enable_connect_protocol(self, value: u32) -> Self {
+        self.setting(Http2SettingId::EnableConnectProtocol, value)
+    }
+
+    #[inline]
+    pub fn no_rfc7540_priorities(self, value: u32) -> Self {
+        self.setting(Http2SettingId::NoRfc7540Priorities, value)
+    }
+
+    /// Check for duplicated parameters and out-of-range values
+    pub fn build(self) -> Result<Http2Settings, ErrorType> {
+        for (idx, setting) in self.settings.iter().enumerate() {
+            let id = u16::from(setting.id);
+            if self.settings[..idx].iter().any(|s| u16::from(s.id) == id) {
+                return Err(ErrorType::InvalidConfig(format!(
+                    "http2 setting {:?} is duplicated",
+                    setting.id
+                )));
+            }
+
+            // `Unknown` may be created with a known id
+            let range = Http2SettingId::from(id).valid_range();
+            if !range.contains(&setting.value) {
+                return Err(ErrorType::InvalidConfig(format!(
+                    "http2 setting {:?} must be in {range:?}, got {}",
+                    setting.id, setting.value
+                )));
+            }
+        }
+
+        Ok(Http2Settings {
+            settings: self.settings,
+        })
+    }
+}
+
+/// PRIORITY frame sent on a fresh connection right after SETTINGS, see RFC 9113, Section 6.3.
+///
+/// Firefox builds a tree of idle streams this way and makes its requests depend on them,
+/// see [`ImpersonationConfig::http2_priority_frames`](super::ImpersonationConfig).
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
+pub struct Http2PriorityFrame {
+    /// Stream the priority is set for, usually an idle one
+    pub stream_id: u32,
+    /// Dependency and weight, where `weight` is the wire value, one less than the real weight
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "super::serde_support::Http2PriorityParamDef")
+    )]
+    pub priority: Http2PriorityParam,
+}
+
+impl Http2PriorityFrame {
+    #[inline]
+    pub fn new(stream_id: u32, stream_dep: u32, exclusive: bool, weight: u8) -> Self {
+        Self {
+            stream_id,
+            priority: Http2PriorityParam {
+                stream_dep,
+                exclusive,
+                weight,
+            },
+        }
+    }
+
+    /// Check the frame could be sent, a stream can not depend on itself
+    pub(crate) fn validate(&self) -> Result<(), ErrorType> {
+        if self.stream_id == 0 || self.stream_id > i32::MAX as u32 {
+            return Err(ErrorType::InvalidConfig(format!(
+                "http2 PRIORITY frame on invalid stream {}",
+                self.stream_id
+            )));
+        }
+        if self.priority.stream_dep == self.stream_id {
+            return Err(ErrorType::InvalidConfig(format!(
+                "http2 stream {} can not depend on itself",
+                self.stream_id
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl From<Http2PriorityFrame> for Http2PriorityFrameFfi {
+    #[inline]
+    fn from(value: Http2PriorityFrame) -> Self {
+        Self {
+            stream_id: value.stream_id,
+            priority: value.priority,
+        }
+    }
+}
+
+/// Extensible priority of RFC 9218, sent as the `priority` header by newer browsers
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtensiblePriority {
+    /// From 0 to 7, lower is more urgent, 3 by default
+    pub urgency: u8,
+    /// Whether the response can be processed incrementally
+    pub incremental: bool,
+}
+
+impl Default for ExtensiblePriority {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            urgency: Self::DEFAULT_URGENCY,
+            incremental: false,
+        }
+    }
+}
+
+impl ExtensiblePriority {
+    const DEFAULT_URGENCY: u8 = 3;
+
+    #[inline]
+    pub fn new(urgency: u8, incremental: bool) -> Self {
+        Self {
+            urgency,
+            incremental,
+        }
+    }
+
+    /// Value of the `priority` header like `u=0, i`, default parameters are omitted
+    /// except the urgency, as browsers always send one.
+    pub fn header_value(&self) -> Result<HeaderValue, ErrorType> {
+        if self.urgency > 7 {
+            return Err(ErrorType::InvalidConfig(format!(
+                "priority urgency must be in 0..=7, got {}",
+                self.urgency
+            )));
+        }
+        let value = if self.incremental {
+            format!("u={}, i", self.urgency)
+        } else {
+            format!("u={}", self.urgency)
+        };
+        Ok(HeaderValue::from_str(&value).expect("valid header value"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_http2_settings_builder() {
+        let settings = Http2Settings::builder()
+            .initial_window_size(6291456)
+            .header_table_size(65536)
+            .setting(0x0a0a, 0)
+            .enable_push(0)
+            .build()
+            .unwrap();
+        let ids: Vec<u16> = settings.iter().map(|s| s.id.into()).collect();
+        assert_eq!(ids, vec![0x4, 0x1, 0x0a0a, 0x2]);
+        assert_eq!(settings.get(Http2SettingId::HeaderTableSize), Some(65536));
+        assert_eq!(settings.get(Http2SettingId::MaxFrameSize), None);
+
+        for builder in [
+            Http2Settings::builder()
+                .header_table_size(65536)
+                .header_table_size(4096),
+            Http2Settings::builder()
+                .enable_push(0)
+                .setting(Http2SettingId::Unknown(0x2), 0),
+            Http2Settings::builder().initial_window_size(1 << 31),
+            Http2Settings::builder().max_frame_size(1024),
+            Http2Settings::builder().enable_push(2),
+        ] {
+            assert!(
+                matches!(builder.clone().build(), Err(ErrorType::InvalidConfig(_))),
+                "{builder:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_priority() {
+        assert!(Http2PriorityFrame::new(3, 0, false, 200).validate().is_ok());
+        assert!(Http2PriorityFrame::new(0, 0, false, 200)
+            .validate()
+            .is_err());
+        assert!(Http2PriorityFrame::new(5, 5, false, 200)
+            .validate()
+            .is_err());
+
+        assert_eq!(
+            ExtensiblePriority::new(0, true).header_value().unwrap(),
+            "u=0, i"
+        );
+        assert_eq!(ExtensiblePriority::default().header_value().unwrap(), "u=3");
+        assert!(ExtensiblePriority::new(8, false).header_value().is_err());
+    }
+}
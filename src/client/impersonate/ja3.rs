@@ -0,0 +1,405 @@
+use super::*;
+use crate::error::fingerprint_error::FingerprintError;
+
+/// X25519Kyber768Draft00 and X25519MLKEM768, hybrid groups sent along with X25519 key share
+const HYBRID_GROUPS: [u16; 2] = [0x6399, 0x11ec];
+/// encrypt_then_mac and post_handshake_auth, which have no data and are sent as
+/// [`TlsExtension::Raw`]
+const EMPTY_EXTENSIONS: [u16; 2] = [22, 49];
+
+/// Contents of the extensions which are not captured by JA3.
+///
+/// The default values are the ones used by Chrome.
+#[derive(Debug, Clone)]
+pub struct Ja3Options {
+    /// For `signature_algorithms` (13)
+    pub signature_algorithms: Vec<SignatureScheme>,
+    /// For `signature_algorithms_cert` (50)
+    pub signature_algorithms_cert: Vec<SignatureScheme>,
+    /// For `application_layer_protocol_negotiation` (16)
+    pub alpn: Vec<String>,
+    /// For `application_settings` (17513)
+    pub application_settings: Vec<String>,
+    /// For `supported_versions` (43), `None` for TLS 1.3 and TLS 1.2, with a GREASE
+    /// value first if the JA3 string contains any GREASE value.
+    pub supported_versions: Option<Vec<u16>>,
+    /// For `key_share` (51), `None` for the first group in `supported_groups`, with a
+    /// GREASE one first if `supported_groups` contains it, and with X25519 if the first
+    /// group is a post-quantum hybrid one.
+    pub key_share_groups: Option<Vec<CurveID>>,
+    /// For `psk_key_exchange_modes` (45)
+    pub psk_modes: Vec<u8>,
+    /// For `compress_certificate` (27)
+    pub cert_compression_algorithms: Vec<u16>,
+    /// For `delegated_credentials` (34)
+    pub delegated_credentials: Vec<SignatureScheme>,
+    /// Data of the extensions without a dedicated [`TlsExtension`] variant, sent as
+    /// [`TlsExtension::Raw`]. Contains `record_size_limit` (28) of Firefox by default.
+    pub raw_extensions: BTreeMap<u16, Vec<u8>>,
+}
+
+impl Default for Ja3Options {
+    fn default() -> Self {
+        let signature_algorithms = vec![
+            SignatureScheme::ECDSAWithP256AndSHA256,
+            SignatureScheme::PSSWithSHA256,
+            SignatureScheme::PKCS1WithSHA256,
+            SignatureScheme::ECDSAWithP384AndSHA384,
+            SignatureScheme::PSSWithSHA384,
+            SignatureScheme::PKCS1WithSHA384,
+            SignatureScheme::PSSWithSHA512,
+            SignatureScheme::PKCS1WithSHA512,
+        ];
+        Self {
+            signature_algorithms_cert: signature_algorithms.clone(),
+            signature_algorithms,
+            alpn: vec!["h2".to_string(), "http/1.1".to_string()],
+            application_settings: vec!["h2".to_string()],
+            supported_versions: None,
+            key_share_groups: None,
+            psk_modes: vec![1],
+            cert_compression_algorithms: vec![0x0002],
+            delegated_credentials: vec![
+                SignatureScheme::ECDSAWithP256AndSHA256,
+                SignatureScheme::ECDSAWithP384AndSHA384,
+                SignatureScheme::ECDSAWithP521AndSHA512,
+                SignatureScheme::ECDSAWithSHA1,
+            ],
+            // 2^14 + 1
+            raw_extensions: BTreeMap::from([(28, vec![0x40, 0x01])]),
+        }
+    }
+}
+
+impl Ja3Options {
+    #[inline]
+    pub fn signature_algorithms(mut self, schemes: Vec<SignatureScheme>) -> Self {
+        self.signature_algorithms = schemes;
+        self
+    }
+
+    #[inline]
+    pub fn signature_algorithms_cert(mut self, schemes: Vec<SignatureScheme>) -> Self {
+        self.signature_algorithms_cert = schemes;
+        self
+    }
+
+    #[inline]
+    pub fn alpn(mut self, protocols: Vec<String>) -> Self {
+        self.alpn = protocols;
+        self
+    }
+
+    #[inline]
+    pub fn application_settings(mut self, protocols: Vec<String>) -> Self {
+        self.application_settings = protocols;
+        self
+    }
+
+    #[inline]
+    pub fn supported_versions(mut self, versions: Vec<u16>) -> Self {
+        self.supported_versions = Some(versions);
+        self
+    }
+
+    #[inline]
+    pub fn key_share_groups(mut self, groups: Vec<CurveID>) -> Self {
+        self.key_share_groups = Some(groups);
+        self
+    }
+
+    #[inline]
+    pub fn psk_modes(mut self, modes: Vec<u8>) -> Self {
+        self.psk_modes = modes;
+        self
+    }
+
+    #[inline]
+    pub fn cert_compression_algorithms(mut self, algorithms: Vec<u16>) -> Self {
+        self.cert_compression_algorithms = algorithms;
+        self
+    }
+
+    #[inline]
+    pub fn delegated_credentials(mut self, schemes: Vec<SignatureScheme>) -> Self {
+        self.delegated_credentials = schemes;
+        self
+    }
+
+    /// Set data of an extension without a dedicated [`TlsExtension`] variant
+    #[inline]
+    pub fn raw_extension(mut self, ext_type: u16, data: Vec<u8>) -> Self {
+        self.raw_extensions.insert(ext_type, data);
+        self
+    }
+}
+
+impl ClientHelloSpec {
+    /// Build the spec from a JA3 string like `771,4865-4866-4867,0-23-65281,29-23-24,0`,
+    /// see [`Ja3Options`] for the default contents of the extensions JA3 does not capture.
+    #[inline]
+    pub fn from_ja3(ja3: &str) -> Result<Self, FingerprintError> {
+        Self::from_ja3_with(ja3, &Ja3Options::default())
+    }
+
+    /// Build the spec from a JA3 string, with given contents of the extensions JA3 does
+    /// not capture.
+    ///
+    /// Extensions are kept in the order of the JA3 string, without shuffling.
+    pub fn from_ja3_with(ja3: &str, options: &Ja3Options) -> Result<Self, FingerprintError> {
+        let invalid = |reason: &str| FingerprintError::InvalidJa3(format!("{reason}: {ja3}"));
+
+        let fields: Vec<&str> = ja3.trim().split(',').collect();
+        let [version, ciphers, extensions, curves, points] = fields[..] else {
+            return Err(invalid("expect 5 fields"));
+        };
+
+        let version: u16 = version
+            .parse()
+            .map_err(|_| invalid("invalid TLS version"))?;
+        let legacy_version = match version {
+            0x0301 => TlsVersion::VersionTLS10,
+            0x0302 => TlsVersion::VersionTLS11,
+            0x0303 => TlsVersion::VersionTLS12,
+            _ => return Err(invalid("unsupported TLS version")),
+        };
+        let cipher_suites = parse_list::<u16>(ciphers).ok_or_else(|| invalid("invalid ciphers"))?;
+        let ext_types =
+            parse_list::<u16>(extensions).ok_or_else(|| invalid("invalid extensions"))?;
+        let curves = parse_list::<u16>(curves).ok_or_else(|| invalid("invalid curves"))?;
+        let points = parse_list::<u8>(points).ok_or_else(|| invalid("invalid point formats"))?;
+
+        let has_grease = cipher_suites
+            .iter()
+            .chain(&ext_types)
+            .chain(&curves)
+            .any(|&v| is_grease(v));
+
+        let extensions = ext_types
+            .iter()
+            .map(|&ext_type| {
+                let ext = match ext_type {
+                    ext_type if is_grease(ext_type) => TlsExtension::TLSGrease,
+                    0 => TlsExtension::ServerName,
+                    5 => TlsExtension::StatusRequest,
+                    10 => TlsExtension::SupportedCurves(
+                        curves.iter().map(|&id| CurveID::from_id(id)).collect(),
+                    ),
+                    11 => TlsExtension::SupportedPoints(points.clone()),
+                    13 => TlsExtension::SignatureAlgorithms(options.signature_algorithms.clone()),
+                    16 => TlsExtension::ALPN(options.alpn.clone()),
+                    17 => TlsExtension::StatusRequestV2,
+                    18 => TlsExtension::SCT,
+                    21 => TlsExtension::UtlsExtensionPadding,
+                    23 => TlsExtension::ExtendedMasterSecret,
+                    27 => TlsExtension::UtlsExtensionCompressCertificate(
+                        options.cert_compression_algorithms.clone(),
+                    ),
+                    34 => TlsExtension::FakeExtensionDelegatedCredentials(
+                        options.delegated_credentials.clone(),
+                    ),
+                    35 => TlsExtension::SessionTicket,
+                    41 => TlsExtension::FakeExtensionPreSharedKey,
+                    43 => TlsExtension::SupportedVersions(
+                        options.supported_versions.clone().unwrap_or_else(|| {
+                            let mut versions = Vec::with_capacity(3);
+                            if has_grease {
+                                versions.push(GREASE_PLACEHOLDER);
+                            }
+                            versions.push(TlsVersion::VersionTLS13 as u16);
+                            versions.push(TlsVersion::VersionTLS12 as u16);
+                            versions
+                        }),
+                    ),
+                    45 => TlsExtension::PSKModes(options.psk_modes.clone()),
+                    50 => TlsExtension::SignatureAlgorithmsCert(
+                        options.signature_algorithms_cert.clone(),
+                    ),
+                    51 => TlsExtension::KeyShare(
+                        options
+                            .key_share_groups
+                            .clone()
+                            .unwrap_or_else(|| default_key_share_groups(&curves))
+                            .into_iter()
+                            .map(|group| {
+                                // GREASE key share carries a single byte
+                                let data = if is_grease(group.id()) {
+                                    vec![0x00]
+                                } else {
+                                    vec![]
+                                };
+                                (group, data)
+                            })
+                            .collect(),
+                    ),
+                    57 => TlsExtension::QUICTransportParameters,
+                    0xff01 => TlsExtension::RenegotiationInfo(1, vec![]),
+                    17513 => TlsExtension::UtlsExtensionApplicationSettings(
+                        options.application_settings.clone(),
+                    ),
+                    0xfe0d => TlsExtension::UtlsExtensionECH,
+                    0xfd00 => TlsExtension::UtlsExtensionECHOuterExtensions,
+                    30031 => TlsExtension::FakeOldExtensionChannelID(true),
+                    30032 => TlsExtension::FakeExtensionChannelID(true),
+                    ext_type => match options.raw_extensions.get(&ext_type) {
+                        Some(data) => TlsExtension::Raw {
+                            ext_type,
+                            data: data.clone(),
+                        },
+                        None if EMPTY_EXTENSIONS.contains(&ext_type) => TlsExtension::Raw {
+                            ext_type,
+                            data: vec![],
+                        },
+                        None => return Err(FingerprintError::UnsupportedExtension(ext_type)),
+                    },
+                };
+                Ok(ext)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // JA3 records the legacy version, TLS 1.3 is only negotiated with supported_versions
+        let tls_version_max = if ext_types.contains(&43) {
+            TlsVersion::VersionTLS13
+        } else {
+            legacy_version.clone()
+        };
+
+        Ok(Self::new()
+            .set_cipher_suites(
+                cipher_suites
+                    .into_iter()
+                    .map(|c| if is_grease(c) { GREASE_PLACEHOLDER } else { c })
+                    .collect(),
+            )
+            .set_extensions(false, extensions)
+            .set_tls_version_min(legacy_version)
+            .set_tls_version_max(tls_version_max))
+    }
+}
+
+/// The first group (with X25519 if it is a hybrid one), after a GREASE one if any
+fn default_key_share_groups(curves: &[u16]) -> Vec<CurveID> {
+    let mut groups = Vec::with_capacity(3);
+    if curves.iter().any(|&c| is_grease(c)) {
+        groups.push(CurveID::Grease);
+    }
+    if let Some(&first) = curves.iter().find(|&&c| !is_grease(c)) {
+        groups.push(CurveID::from_id(first));
+        if HYBRID_GROUPS.contains(&first) && curves.contains(&29) {
+            groups.push(CurveID::X25519);
+        }
+    }
+    groups
+}
+
+/// Parse `-` separated decimal list, empty string for empty list
+fn parse_list<T: std::str::FromStr>(list: &str) -> Option<Vec<T>> {
+    if list.is_empty() {
+        return Some(Vec::new());
+    }
+    list.split('-').map(|v| v.parse().ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHROME_JA3: &str = "771,4865-4866-4867-49195-49199-49196-49200-52393-52392-49171-49172-156-157-47-53,0-23-65281-10-11-35-16-5-13-18-51-45-43-27-17513-65037-21,25497-29-23-24,0";
+
+    #[test]
+    fn test_from_ja3() {
+        let spec = ClientHelloSpec::from_ja3(CHROME_JA3).unwrap();
+        assert_eq!(spec.cipher_suites.len(), 15);
+        assert_eq!(spec.cipher_suites[0], TLS_AES_128_GCM_SHA256);
+        assert_eq!(spec.extensions.len(), 17);
+        assert!(matches!(spec.tls_version_max, TlsVersion::VersionTLS13));
+        assert!(matches!(spec.tls_version_min, TlsVersion::VersionTLS12));
+
+        let key_share = spec
+            .extensions
+            .iter()
+            .find_map(|ext| match ext {
+                TlsExtension::KeyShare(shares) => Some(
+                    shares
+                        .iter()
+                        .map(|(group, _)| group.id())
+                        .collect::<Vec<_>>(),
+                ),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(key_share, vec![0x6399, 29]);
+
+        assert!(spec.extensions.iter().any(|ext| matches!(
+            ext,
+            TlsExtension::SupportedVersions(versions) if versions == &[0x0304, 0x0303]
+        )));
+    }
+
+    #[test]
+    fn test_from_ja3_with_options() {
+        let options = Ja3Options::default()
+            .alpn(vec!["http/1.1".to_string()])
+            .key_share_groups(vec![CurveID::X25519, CurveID::CurveP256]);
+        let ja3 = "771,2570-4865-4866,2570-0-16-10-51-43,2570-29-23,0";
+        let spec = ClientHelloSpec::from_ja3_with(ja3, &options).unwrap();
+
+        assert_eq!(spec.cipher_suites[0], GREASE_PLACEHOLDER);
+        assert!(matches!(spec.extensions[0], TlsExtension::TLSGrease));
+        assert!(matches!(
+            &spec.extensions[2],
+            TlsExtension::ALPN(protocols) if protocols == &["http/1.1"]
+        ));
+        assert!(matches!(
+            &spec.extensions[4],
+            TlsExtension::KeyShare(shares) if shares.len() == 2
+        ));
+        assert!(matches!(
+            &spec.extensions[5],
+            TlsExtension::SupportedVersions(versions) if versions[0] == GREASE_PLACEHOLDER
+        ));
+    }
+
+    #[test]
+    fn test_from_ja3_raw_extensions() {
+        const FIREFOX_JA3: &str = "771,4865-4867-4866-49195-49199,0-23-65281-10-11-35-16-5-34-51-43-13-45-28-65037,4588-29-23-24-25-256-257,0";
+        let spec = ClientHelloSpec::from_ja3(FIREFOX_JA3).unwrap();
+        assert!(spec.extensions.iter().any(|ext| matches!(
+            ext,
+            TlsExtension::Raw { ext_type: 28, data } if data == &[0x40, 0x01]
+        )));
+        assert_eq!(spec.ja3(), FIREFOX_JA3);
+
+        let options = Ja3Options::default().raw_extension(44, vec![0x00, 0x01, 0xff]);
+        let spec = ClientHelloSpec::from_ja3_with("771,4865,0-22-44-49,29,0", &options).unwrap();
+        let raw: Vec<_> = spec
+            .extensions
+            .iter()
+            .filter_map(|ext| match ext {
+                TlsExtension::Raw { ext_type, data } => Some((*ext_type, data.as_slice())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            raw,
+            [(22, &[][..]), (44, &[0x00, 0x01, 0xff][..]), (49, &[][..])]
+        );
+    }
+
+    #[test]
+    fn test_from_ja3_error() {
+        assert!(matches!(
+            ClientHelloSpec::from_ja3("771,4865,0-44,29,0"),
+            Err(FingerprintError::UnsupportedExtension(44))
+        ));
+        for invalid in ["", "771,4865,0,29", "771,4865,0-x,29,0", "772,4865,0,29,0"] {
+            assert!(
+                matches!(
+                    ClientHelloSpec::from_ja3(invalid),
+                    Err(FingerprintError::InvalidJa3(_))
+                ),
+                "{invalid} should be rejected"
+            );
+        }
+    }
+}
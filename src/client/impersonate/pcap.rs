@@ -0,0 +1,424 @@
+//! Minimal reader of pcap and pcapng captures, to extract ClientHello sent by a real client.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::IpAddr,
+};
+
+use super::client_hello::handshake_message;
+use crate::error::fingerprint_error::FingerprintError;
+
+const PCAP_MAGIC_MICROS: u32 = 0xa1b2_c3d4;
+const PCAP_MAGIC_NANOS: u32 = 0xa1b2_3c4d;
+const PCAPNG_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+const PCAPNG_INTERFACE_DESCRIPTION: u32 = 1;
+const PCAPNG_SIMPLE_PACKET: u32 = 3;
+const PCAPNG_ENHANCED_PACKET: u32 = 6;
+
+const LINKTYPE_NULL: u16 = 0;
+const LINKTYPE_ETHERNET: u16 = 1;
+const LINKTYPE_RAW: [u16; 5] = [12, 14, 101, 228, 229];
+const LINKTYPE_LOOP: u16 = 108;
+const LINKTYPE_LINUX_SLL: u16 = 113;
+const LINKTYPE_LINUX_SLL2: u16 = 276;
+
+const TCP_SYN: u8 = 0x02;
+/// Flows whose first bytes are not a ClientHello are ignored once they reach this size
+const MAX_CLIENT_HELLO_LEN: usize = 1 << 16;
+
+/// Find the first ClientHello in a pcap or pcapng capture, reassembling it from TCP
+/// segments if needed.
+///
+/// The returned handshake message can be passed to [`ClientHello::parse`](super::ClientHello::parse).
+pub fn first_client_hello(capture: &[u8]) -> Result<Vec<u8>, FingerprintError> {
+    let mut flows = Flows::default();
+    let magic = read_u32(capture, 0, true).ok_or_else(|| invalid("truncated header"))?;
+    let found = if magic == PCAPNG_SECTION_HEADER {
+        read_pcapng(capture, &mut flows)?
+    } else {
+        read_pcap(capture, &mut flows)?
+    };
+    found.ok_or_else(|| invalid("no ClientHello found"))
+}
+
+/// Classic pcap, in either byte order and timestamp precision
+fn read_pcap(capture: &[u8], flows: &mut Flows) -> Result<Option<Vec<u8>>, FingerprintError> {
+    let le = match read_u32(capture, 0, true) {
+        Some(PCAP_MAGIC_MICROS | PCAP_MAGIC_NANOS) => true,
+        _ => match read_u32(capture, 0, false) {
+            Some(PCAP_MAGIC_MICROS | PCAP_MAGIC_NANOS) => false,
+            _ => return Err(invalid("unknown file format")),
+        },
+    };
+    let link_type = read_u32(capture, 20, le).ok_or_else(|| invalid("truncated header"))? as u16;
+
+    let mut offset = 24;
+    while offset < capture.len() {
+        let captured_len =
+            read_u32(capture, offset + 8, le).ok_or_else(|| invalid("truncated packet"))? as usize;
+        let packet = capture
+            .get(offset + 16..offset + 16 + captured_len)
+            .ok_or_else(|| invalid("truncated packet"))?;
+        if let Some(hello) = flows.feed(link_type, packet) {
+            return Ok(Some(hello));
+        }
+        offset += 16 + captured_len;
+    }
+    Ok(None)
+}
+
+/// pcapng, with possibly multiple sections and interfaces
+fn read_pcapng(capture: &[u8], flows: &mut Flows) -> Result<Option<Vec<u8>>, FingerprintError> {
+    let mut le = true;
+    let mut link_types = Vec::new();
+
+    let mut offset = 0;
+    while offset < capture.len() {
+        let block_type = read_u32(capture, offset, le).ok_or_else(|| invalid("truncated block"))?;
+        if block_type == PCAPNG_SECTION_HEADER {
+            le = read_u32(capture, offset + 8, true) == Some(PCAPNG_BYTE_ORDER_MAGIC);
+            link_types.clear();
+        }
+        let block_len =
+            read_u32(capture, offset + 4, le).ok_or_else(|| invalid("truncated block"))? as usize;
+        if block_len < 12 || block_len % 4 != 0 {
+            return Err(invalid("invalid block length"));
+        }
+        let block = capture
+            .get(offset..offset + block_len)
+            .ok_or_else(|| invalid("truncated block"))?;
+
+        let packet = match block_type {
+            PCAPNG_INTERFACE_DESCRIPTION => {
+                link_types.push(read_u16(block, 8, le).ok_or_else(|| invalid("truncated block"))?);
+                None
+            }
+            PCAPNG_ENHANCED_PACKET => {
+                let interface = read_u32(block, 8, le).ok_or_else(|| invalid("truncated block"))?;
+                let captured_len =
+                    read_u32(block, 20, le).ok_or_else(|| invalid("truncated block"))? as usize;
+                let packet = block
+                    .get(28..28 + captured_len)
+                    .ok_or_else(|| invalid("truncated block"))?;
+                Some((interface as usize, packet))
+            }
+            PCAPNG_SIMPLE_PACKET => {
+                let original_len =
+                    read_u32(block, 8, le).ok_or_else(|| invalid("truncated block"))? as usize;
+                let end = (12 + original_len).min(block_len - 4);
+                let packet = block
+                    .get(12..end)
+                    .ok_or_else(|| invalid("truncated block"))?;
+                Some((0, packet))
+            }
+            _ => None,
+        };
+
+        if let Some((interface, packet)) = packet {
+            let link_type = *link_types
+                .get(interface)
+                .ok_or_else(|| invalid("packet of unknown interface"))?;
+            if let Some(hello) = flows.feed(link_type, packet) {
+                return Ok(Some(hello));
+            }
+        }
+        offset += block_len;
+    }
+    Ok(None)
+}
+
+/// Client side of a TCP connection
+type FlowKey = (IpAddr, u16, IpAddr, u16);
+
+#[derive(Default)]
+struct Flows {
+    flows: HashMap<FlowKey, Flow>,
+}
+
+#[derive(Default)]
+struct Flow {
+    /// Sequence number of the first payload byte
+    start: Option<u32>,
+    /// Payload by its offset from `start`
+    segments: BTreeMap<u32, Vec<u8>>,
+    /// Not a TLS flow, or its first bytes are not a ClientHello
+    ignored: bool,
+}
+
+impl Flows {
+    /// Feed a captured packet, returns the ClientHello once one is complete
+    fn feed(&mut self, link_type: u16, packet: &[u8]) -> Option<Vec<u8>> {
+        let (key, segment) = tcp_segment(link_type, packet)?;
+        let flow = self.flows.entry(key).or_default();
+        if flow.ignored {
+            return None;
+        }
+        if segment.flags & TCP_SYN != 0 {
+            flow.start = Some(segment.seq.wrapping_add(1));
+            flow.segments.clear();
+        }
+        if segment.payload.is_empty() {
+            return None;
+        }
+
+        let start = *flow.start.get_or_insert(segment.seq);
+        let offset = segment.seq.wrapping_sub(start);
+        if offset >= MAX_CLIENT_HELLO_LEN as u32 {
+            // retransmission of data before `start`, or data after the ClientHello
+            return None;
+        }
+        let existing = flow.segments.entry(offset).or_default();
+        if existing.len() < segment.payload.len() {
+            *existing = segment.payload.to_vec();
+        }
+
+        let mut stream = Vec::new();
+        for (&offset, payload) in &flow.segments {
+            let offset = offset as usize;
+            if offset > stream.len() {
+                break;
+            }
+            if offset + payload.len() > stream.len() {
+                stream.extend_from_slice(&payload[stream.len() - offset..]);
+            }
+        }
+
+        match handshake_message(&stream) {
+            Ok(Some(hello)) => Some(hello),
+            Ok(None) if stream.len() < MAX_CLIENT_HELLO_LEN => None,
+            _ => {
+                flow.ignored = true;
+                flow.segments.clear();
+                None
+            }
+        }
+    }
+}
+
+struct TcpSegment<'a> {
+    seq: u32,
+    flags: u8,
+    payload: &'a [u8],
+}
+
+/// Decode link, IP and TCP layers, `None` for packets of other protocols
+fn tcp_segment(link_type: u16, packet: &[u8]) -> Option<(FlowKey, TcpSegment<'_>)> {
+    let ip = match link_type {
+        LINKTYPE_NULL | LINKTYPE_LOOP => packet.get(4..)?,
+        LINKTYPE_ETHERNET => {
+            let mut offset = 12;
+            let mut ether_type = read_u16(packet, offset, false)?;
+            // 802.1Q and 802.1ad VLAN tags
+            while ether_type == 0x8100 || ether_type == 0x88a8 {
+                offset += 4;
+                ether_type = read_u16(packet, offset, false)?;
+            }
+            packet.get(offset + 2..)?
+        }
+        LINKTYPE_LINUX_SLL => packet.get(16..)?,
+        LINKTYPE_LINUX_SLL2 => packet.get(20..)?,
+        link_type if LINKTYPE_RAW.contains(&link_type) => packet,
+        _ => return None,
+    };
+
+    let (src, dst, tcp) = match ip.first()? >> 4 {
+        4 => {
+            let header_len = ((ip[0] & 0x0f) as usize) * 4;
+            let total_len = read_u16(ip, 2, false)? as usize;
+            let fragment = read_u16(ip, 6, false)?;
+            // TCP and fragmented packets only, the latter are rare for ClientHello
+            if ip.get(9)? != &6 || fragment & 0x3fff != 0 {
+                return None;
+            }
+            let src: [u8; 4] = ip.get(12..16)?.try_into().ok()?;
+            let dst: [u8; 4] = ip.get(16..20)?.try_into().ok()?;
+            // Ethernet frames may be padded
+            let tcp = ip.get(header_len..total_len.min(ip.len()))?;
+            (IpAddr::from(src), IpAddr::from(dst), tcp)
+        }
+        6 => {
+            let payload_len = read_u16(ip, 4, false)? as usize;
+            let src: [u8; 16] = ip.get(8..24)?.try_into().ok()?;
+            let dst: [u8; 16] = ip.get(24..40)?.try_into().ok()?;
+            let mut next_header = *ip.get(6)?;
+            let mut payload = ip.get(40..(40 + payload_len).min(ip.len()))?;
+            // hop-by-hop, routing and destination options
+            while matches!(next_header, 0 | 43 | 60) {
+                next_header = *payload.first()?;
+                let len = (*payload.get(1)? as usize + 1) * 8;
+                payload = payload.get(len..)?;
+            }
+            if next_header != 6 {
+                return None;
+            }
+            (IpAddr::from(src), IpAddr::from(dst), payload)
+        }
+        _ => return None,
+    };
+
+    let src_port = read_u16(tcp, 0, false)?;
+    let dst_port = read_u16(tcp, 2, false)?;
+    let seq = read_u32(tcp, 4, false)?;
+    let header_len = ((tcp.get(12)? >> 4) as usize) * 4;
+    let flags = *tcp.get(13)?;
+    let payload = tcp.get(header_len..)?;
+    Some((
+        (src, src_port, dst, dst_port),
+        TcpSegment {
+            seq,
+            flags,
+            payload,
+        },
+    ))
+}
+
+#[inline]
+fn read_u16(data: &[u8], offset: usize, le: bool) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?.try_into().ok()?;
+    Some(if le {
+        u16::from_le_bytes(bytes)
+    } else {
+        u16::from_be_bytes(bytes)
+    })
+}
+
+#[inline]
+fn read_u32(data: &[u8], offset: usize, le: bool) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?.try_into().ok()?;
+    Some(if le {
+        u32::from_le_bytes(bytes)
+    } else {
+        u32::from_be_bytes(bytes)
+    })
+}
+
+#[inline]
+fn invalid(reason: &str) -> FingerprintError {
+    FingerprintError::InvalidCapture(reason.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::client_hello::tests::{hex, OPENSSL_CLIENT_HELLO};
+    use super::super::ClientHello;
+    use super::*;
+
+    const CLIENT: [u8; 4] = [192, 168, 1, 2];
+    const SERVER: [u8; 4] = [93, 184, 215, 14];
+
+    /// Ethernet frame of IPv4 TCP segment
+    fn frame(src_port: u16, seq: u32, flags: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0; 12];
+        frame.extend_from_slice(&[0x08, 0x00]);
+        frame.extend_from_slice(&[0x45, 0]);
+        frame.extend_from_slice(&(40 + payload.len() as u16).to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0x40, 0, 64, 6, 0, 0]);
+        frame.extend_from_slice(&CLIENT);
+        frame.extend_from_slice(&SERVER);
+        frame.extend_from_slice(&src_port.to_be_bytes());
+        frame.extend_from_slice(&443u16.to_be_bytes());
+        frame.extend_from_slice(&seq.to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0, 0, 0x50, flags, 0xff, 0xff, 0, 0, 0, 0]);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    /// Frames of a flow whose hello is split into 3 segments, delivered out of order
+    fn frames(hello: &[u8]) -> Vec<Vec<u8>> {
+        let isn = u32::MAX - 10; // sequence number wraps
+        let seq = |offset: usize| isn.wrapping_add(1).wrapping_add(offset as u32);
+        vec![
+            frame(50000, isn, TCP_SYN, &[]),
+            frame(50001, 7, 0x18, b"GET / HTTP/1.1\r\n\r\n"),
+            frame(50000, seq(0), 0x18, &hello[..200]),
+            frame(50000, seq(400), 0x18, &hello[400..]),
+            frame(50000, seq(0), 0x18, &hello[..100]),
+            frame(50000, seq(200), 0x18, &hello[200..400]),
+        ]
+    }
+
+    #[test]
+    fn test_pcap() {
+        let hello = hex(OPENSSL_CLIENT_HELLO);
+        let expected = ClientHello::parse(&hello).unwrap().spec.ja3();
+
+        for le in [true, false] {
+            let u32_bytes = |v: u32| if le { v.to_le_bytes() } else { v.to_be_bytes() };
+            let mut capture = Vec::new();
+            capture.extend_from_slice(&u32_bytes(PCAP_MAGIC_NANOS));
+            capture.extend_from_slice(&[0; 16]);
+            capture.extend_from_slice(&u32_bytes(LINKTYPE_ETHERNET as u32));
+            let mut complete_len = 0;
+            for frame in frames(&hello) {
+                complete_len = capture.len();
+                capture.extend_from_slice(&[0; 8]);
+                capture.extend_from_slice(&u32_bytes(frame.len() as u32));
+                capture.extend_from_slice(&u32_bytes(frame.len() as u32));
+                capture.extend_from_slice(&frame);
+            }
+
+            let message = first_client_hello(&capture).unwrap();
+            assert_eq!(message, &hello[5..]);
+            assert_eq!(
+                ClientHello::from_capture(&capture).unwrap().spec.ja3(),
+                expected
+            );
+
+            // without the last segment, the hello is never complete
+            capture.truncate(complete_len);
+            assert!(matches!(
+                first_client_hello(&capture),
+                Err(FingerprintError::InvalidCapture(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_pcapng() {
+        let hello = hex(OPENSSL_CLIENT_HELLO);
+        let block = |block_type: u32, body: &[u8]| {
+            let padded = body.len().next_multiple_of(4);
+            let len = (padded + 12) as u32;
+            let mut block = Vec::new();
+            block.extend_from_slice(&block_type.to_le_bytes());
+            block.extend_from_slice(&len.to_le_bytes());
+            block.extend_from_slice(body);
+            block.resize(8 + padded, 0);
+            block.extend_from_slice(&len.to_le_bytes());
+            block
+        };
+
+        let mut capture = Vec::new();
+        let mut shb = PCAPNG_BYTE_ORDER_MAGIC.to_le_bytes().to_vec();
+        shb.extend_from_slice(&[1, 0, 0, 0]);
+        shb.extend_from_slice(&u64::MAX.to_le_bytes());
+        capture.extend(block(PCAPNG_SECTION_HEADER, &shb));
+        capture.extend(block(
+            PCAPNG_INTERFACE_DESCRIPTION,
+            &[1, 0, 0, 0, 0, 0, 0, 0],
+        ));
+        let header_len = capture.len();
+        for frame in frames(&hello) {
+            let mut epb = vec![0; 12];
+            epb.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            epb.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            epb.extend_from_slice(&frame);
+            capture.extend(block(PCAPNG_ENHANCED_PACKET, &epb));
+        }
+
+        assert_eq!(first_client_hello(&capture).unwrap(), &hello[5..]);
+        assert!(matches!(
+            first_client_hello(&capture[..40]),
+            Err(FingerprintError::InvalidCapture(_))
+        ));
+
+        // simple packet block without room for the original length
+        let mut truncated = capture[..header_len].to_vec();
+        truncated.extend(block(PCAPNG_SIMPLE_PACKET, &[]));
+        assert!(matches!(
+            first_client_hello(&truncated),
+            Err(FingerprintError::InvalidCapture(_))
+        ));
+    }
+}
@@ -0,0 +1,938 @@
+//! Built-in browser profiles.
+//!
+//! Each profile keeps TLS, HTTP2 settings, pseudo header order, header order and
+//! user-agent / client-hint headers consistent with the real browser of that version.
+//!
+//! Fingerprints rarely change between browser releases, so only the versions that
+//! changed them are recorded. The requested version is snapped to the nearest recorded
+//! one at or below it for TLS and HTTP2, while headers like `user-agent` keep the
+//! requested version. Versions older than the oldest recorded one use the oldest profile.
+
+use std::collections::BTreeMap;
+
+use http::{HeaderMap, HeaderName, HeaderValue};
+
+use super::*;
+
+/// Recorded Chrome (and Edge) versions
+pub const CHROME_VERSIONS: &[u16] = &[120, 124, 131];
+/// Recorded Firefox versions
+pub const FIREFOX_VERSIONS: &[u16] = &[120, 128, 133];
+/// Recorded Safari (and iOS) versions
+pub const SAFARI_VERSIONS: &[u16] = &[16, 17, 18];
+/// Recorded OkHttp major versions
+pub const OKHTTP_VERSIONS: &[u16] = &[3, 4];
+
+/// Chrome on Windows
+pub fn chrome(version: u16) -> ImpersonationConfig {
+    chromium(version, Chromium::Chrome)
+}
+
+/// Edge on Windows, shares the TLS and HTTP2 fingerprint of Chrome
+pub fn edge(version: u16) -> ImpersonationConfig {
+    chromium(version, Chromium::Edge)
+}
+
+/// Firefox on Windows
+pub fn firefox(version: u16) -> ImpersonationConfig {
+    let version = version.max(FIREFOX_VERSIONS[0]);
+    let profile = snap(FIREFOX_VERSIONS, version);
+
+    let mut curves = vec![
+        CurveID::X25519,
+        CurveID::CurveP256,
+        CurveID::CurveP384,
+        CurveID::CurveP521,
+        CurveID::Custom(0x0100), // ffdhe2048
+        CurveID::Custom(0x0101), // ffdhe3072
+    ];
+    let mut key_shares = vec![(CurveID::X25519, vec![]), (CurveID::CurveP256, vec![])];
+    if profile >= 133 {
+        curves.insert(0, CurveID::Custom(X25519_MLKEM768));
+        key_shares.insert(0, (CurveID::Custom(X25519_MLKEM768), vec![]));
+    }
+
+    let spec = ClientHelloSpec::new()
+        .set_cipher_suites(vec![
+            TLS_AES_128_GCM_SHA256,
+            TLS_CHACHA20_POLY1305_SHA256,
+            TLS_AES_256_GCM_SHA384,
+            TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256,
+            TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256,
+            TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305,
+            TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305,
+            TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384,
+            TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384,
+            TLS_ECDHE_ECDSA_WITH_AES_256_CBC_SHA,
+            TLS_ECDHE_ECDSA_WITH_AES_128_CBC_SHA,
+            TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA,
+            TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA,
+            TLS_RSA_WITH_AES_128_GCM_SHA256,
+            TLS_RSA_WITH_AES_256_GCM_SHA384,
+            TLS_RSA_WITH_AES_128_CBC_SHA,
+            TLS_RSA_WITH_AES_256_CBC_SHA,
+        ])
+        .set_extensions(
+            false,
+            vec![
+                TlsExtension::ServerName,
+                TlsExtension::ExtendedMasterSecret,
+                TlsExtension::RenegotiationInfo(1, vec![]),
+                TlsExtension::SupportedCurves(curves),
+                TlsExtension::SupportedPoints(vec![0x00]),
+                TlsExtension::SessionTicket,
+                TlsExtension::ALPN(vec!["h2".to_string(), "http/1.1".to_string()]),
+                TlsExtension::StatusRequest,
+                TlsExtension::FakeExtensionDelegatedCredentials(vec![
+                    SignatureScheme::ECDSAWithP256AndSHA256,
+                    SignatureScheme::ECDSAWithP384AndSHA384,
+                    SignatureScheme::ECDSAWithP521AndSHA512,
+                    SignatureScheme::ECDSAWithSHA1,
+                ]),
+                TlsExtension::KeyShare(key_shares),
+                TlsExtension::SupportedVersions(vec![
+                    TlsVersion::VersionTLS13 as u16,
+                    TlsVersion::VersionTLS12 as u16,
+                ]),
+                TlsExtension::SignatureAlgorithms(vec![
+                    SignatureScheme::ECDSAWithP256AndSHA256,
+                    SignatureScheme::ECDSAWithP384AndSHA384,
+                    SignatureScheme::ECDSAWithP521AndSHA512,
+                    SignatureScheme::PSSWithSHA256,
+                    SignatureScheme::PSSWithSHA384,
+                    SignatureScheme::PSSWithSHA512,
+                    SignatureScheme::PKCS1WithSHA256,
+                    SignatureScheme::PKCS1WithSHA384,
+                    SignatureScheme::PKCS1WithSHA512,
+                    SignatureScheme::ECDSAWithSHA1,
+                    SignatureScheme::PKCS1WithSHA1,
+                ]),
+                TlsExtension::PSKModes(vec![1]),
+                // record_size_limit, 2^14 + 1
+                TlsExtension::Raw {
+                    ext_type: 28,
+                    data: vec![0x40, 0x01],
+                },
+                TlsExtension::UtlsExtensionECH,
+            ],
+        );
+
+    let accept = if profile >= 128 {
+        "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8"
+    } else {
+        "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,*/*;q=0.8"
+    };
+    let accept_encoding = if profile >= 128 {
+        "gzip, deflate, br, zstd"
+    } else {
+        "gzip, deflate, br"
+    };
+
+    let mut common_headers = vec![
+        (
+            "user-agent",
+            format!(
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:{version}.0) Gecko/20100101 Firefox/{version}.0"
+            ),
+        ),
+        ("accept", accept.to_string()),
+        ("accept-language", "en-US,en;q=0.5".to_string()),
+        ("accept-encoding", accept_encoding.to_string()),
+        ("upgrade-insecure-requests", "1".to_string()),
+        ("sec-fetch-dest", "document".to_string()),
+        ("sec-fetch-mode", "navigate".to_string()),
+        ("sec-fetch-site", "none".to_string()),
+        ("sec-fetch-user", "?1".to_string()),
+        ("te", "trailers".to_string()),
+    ];
+    if profile >= 128 {
+        common_headers.push(("priority", "u=0, i".to_string()));
+    }
+
+    ImpersonationConfig {
+        utls_config: UTlsConfig {
+            id: ClientHelloId::Custom,
+            spec: Some(spec),
+        },
+        http2_settings_frame: Http2Settings::builder()
+            .header_table_size(65536)
+            .initial_window_size(131072)
+            .max_frame_size(16384)
+            .build()
+            .expect("valid http2 settings"),
+        http2_connection_flow: 12517377,
+        common_pseudo_header_order: pseudo_header_order(":method,:path,:authority,:scheme"),
+        common_header_order: header_order(&[
+            "user-agent",
+            "accept",
+            "accept-language",
+            "accept-encoding",
+            "content-type",
+            "content-length",
+            "origin",
+            "referer",
+            "cookie",
+            "upgrade-insecure-requests",
+            "sec-fetch-dest",
+            "sec-fetch-mode",
+            "sec-fetch-site",
+            "sec-fetch-user",
+            "priority",
+            "pragma",
+            "cache-control",
+            "te",
+        ]),
+        common_headers: header_map(common_headers),
+        http2_header_priority: Http2PriorityParam {
+            stream_dep: 13,
+            exclusive: false,
+            weight: 41,
+        },
+        // idle streams for leaders, followers, unblocked, background, speculative and
+        // urgent-start requests, navigation depends on the last one
+        http2_priority_frames: vec![
+            Http2PriorityFrame::new(3, 0, false, 200),
+            Http2PriorityFrame::new(5, 0, false, 100),
+            Http2PriorityFrame::new(7, 0, false, 0),
+            Http2PriorityFrame::new(9, 7, false, 0),
+            Http2PriorityFrame::new(11, 3, false, 0),
+            Http2PriorityFrame::new(13, 0, false, 240),
+        ],
+        request_kind_headers: kind_headers(
+            [
+                (RequestKind::Fetch, Some("*/*"), "cors", "empty", "u=4"),
+                (RequestKind::Script, Some("*/*"), "no-cors", "script", "u=2"),
+                (
+                    RequestKind::Style,
+                    Some("text/css,*/*;q=0.1"),
+                    "no-cors",
+                    "style",
+                    "u=2",
+                ),
+                (
+                    RequestKind::Image,
+                    Some("image/avif,image/webp,image/png,image/svg+xml,image/*;q=0.8,*/*;q=0.5"),
+                    "no-cors",
+                    "image",
+                    "u=5, i",
+                ),
+                (
+                    RequestKind::Font,
+                    Some("application/font-woff2;q=1.0,application/font-woff;q=0.9,*/*;q=0.8"),
+                    "cors",
+                    "font",
+                    "u=3",
+                ),
+                (
+                    RequestKind::WebSocket,
+                    Some("*/*"),
+                    "websocket",
+                    "websocket",
+                    "",
+                ),
+            ],
+            true,
+            profile >= 128,
+        ),
+    }
+}
+
+/// Safari on macOS
+pub fn safari(version: u16) -> ImpersonationConfig {
+    apple(version, Apple::MacOS)
+}
+
+/// Safari on iOS, shares the TLS and HTTP2 fingerprint of Safari on macOS
+pub fn ios(version: u16) -> ImpersonationConfig {
+    apple(version, Apple::Ios)
+}
+
+/// OkHttp on Android, by major version
+pub fn okhttp(version: u16) -> ImpersonationConfig {
+    let version = version.max(OKHTTP_VERSIONS[0]);
+    let profile = snap(OKHTTP_VERSIONS, version);
+
+    let spec = ClientHelloSpec::new()
+        .set_cipher_suites(vec![
+            TLS_AES_128_GCM_SHA256,
+            TLS_AES_256_GCM_SHA384,
+            TLS_CHACHA20_POLY1305_SHA256,
+            TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256,
+            TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256,
+            TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384,
+            TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384,
+            TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305,
+            TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305,
+            TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA,
+            TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA,
+            TLS_RSA_WITH_AES_128_GCM_SHA256,
+            TLS_RSA_WITH_AES_256_GCM_SHA384,
+            TLS_RSA_WITH_AES_128_CBC_SHA,
+            TLS_RSA_WITH_AES_256_CBC_SHA,
+        ])
+        .set_extensions(
+            false,
+            vec![
+                TlsExtension::ServerName,
+                TlsExtension::ExtendedMasterSecret,
+                TlsExtension::RenegotiationInfo(1, vec![]),
+                TlsExtension::SupportedCurves(vec![
+                    CurveID::X25519,
+                    CurveID::CurveP256,
+                    CurveID::CurveP384,
+                ]),
+                TlsExtension::SupportedPoints(vec![0x00]),
+                TlsExtension::SessionTicket,
+                TlsExtension::ALPN(vec!["h2".to_string(), "http/1.1".to_string()]),
+                TlsExtension::StatusRequest,
+                TlsExtension::SignatureAlgorithms(vec![
+                    SignatureScheme::ECDSAWithP256AndSHA256,
+                    SignatureScheme::PSSWithSHA256,
+                    SignatureScheme::PKCS1WithSHA256,
+                    SignatureScheme::ECDSAWithP384AndSHA384,
+                    SignatureScheme::PSSWithSHA384,
+                    SignatureScheme::PKCS1WithSHA384,
+                    SignatureScheme::PSSWithSHA512,
+                    SignatureScheme::PKCS1WithSHA512,
+                    SignatureScheme::PKCS1WithSHA1,
+                ]),
+                TlsExtension::KeyShare(vec![(CurveID::X25519, vec![])]),
+                TlsExtension::PSKModes(vec![1]),
+                TlsExtension::SupportedVersions(vec![
+                    TlsVersion::VersionTLS13 as u16,
+                    TlsVersion::VersionTLS12 as u16,
+                ]),
+                TlsExtension::UtlsExtensionPadding,
+            ],
+        );
+
+    let user_agent = match profile {
+        3 => "okhttp/3.14.9",
+        _ => "okhttp/4.12.0",
+    };
+
+    ImpersonationConfig {
+        utls_config: UTlsConfig {
+            id: ClientHelloId::Custom,
+            spec: Some(spec),
+        },
+        http2_settings_frame: Http2Settings::builder()
+            .initial_window_size(16777216)
+            .build()
+            .expect("valid http2 settings"),
+        http2_connection_flow: 16711681,
+        common_pseudo_header_order: pseudo_header_order(":method,:path,:authority,:scheme"),
+        common_header_order: header_order(&[
+            "content-type",
+            "content-length",
+            "accept-encoding",
+            "cookie",
+            "user-agent",
+        ]),
+        common_headers: header_map(vec![
+            ("accept-encoding", "gzip".to_string()),
+            ("user-agent", user_agent.to_string()),
+        ]),
+        http2_header_priority: Http2PriorityParam {
+            stream_dep: 0,
+            exclusive: false,
+            weight: 15,
+        },
+        http2_priority_frames: Vec::new(),
+        request_kind_headers: BTreeMap::new(),
+    }
+}
+
+/// Full profile matching the given [`ClientHelloId`], `None` for [`ClientHelloId::Custom`]
+/// or unparsable version.
+///
+/// Only the leading number of the version is used, e.g. `"106_Shuffle"` is treated as 106.
+pub fn from_client_hello_id(id: &ClientHelloId) -> Option<ImpersonationConfig> {
+    let version = || -> Option<u16> {
+        let version = id.version();
+        let end = version
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(version.len());
+        version[..end].parse().ok()
+    };
+
+    Some(match id {
+        ClientHelloId::Chrome(_) => chrome(version()?),
+        ClientHelloId::Edge(_) => edge(version()?),
+        ClientHelloId::Firefox(_) => firefox(version()?),
+        ClientHelloId::Safari(_) => safari(version()?),
+        ClientHelloId::IOS(_) => ios(version()?),
+        ClientHelloId::Android11Okhttp(_) => okhttp(4),
+        ClientHelloId::Custom => return None,
+    })
+}
+
+// === Chromium based ===
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Chromium {
+    Chrome,
+    Edge,
+}
+
+/// X25519Kyber768Draft00, enabled by default since Chrome 124
+const X25519_KYBER768: u16 = 0x6399;
+/// X25519MLKEM768, replaced X25519Kyber768Draft00 since Chrome 131 and Firefox 132
+const X25519_MLKEM768: u16 = 0x11ec;
+
+fn chromium(version: u16, brand: Chromium) -> ImpersonationConfig {
+    let version = version.max(CHROME_VERSIONS[0]);
+    let profile = snap(CHROME_VERSIONS, version);
+
+    let mut curves = vec![
+        CurveID::Grease,
+        CurveID::X25519,
+        CurveID::CurveP256,
+        CurveID::CurveP384,
+    ];
+    let mut key_shares = vec![(CurveID::Grease, vec![0x00]), (CurveID::X25519, vec![])];
+    let post_quantum = match profile {
+        131.. => Some(X25519_MLKEM768),
+        124.. => Some(X25519_KYBER768),
+        _ => None,
+    };
+    if let Some(group) = post_quantum {
+        curves.insert(1, CurveID::Custom(group));
+        key_shares.insert(1, (CurveID::Custom(group), vec![]));
+    }
+
+    let spec = ClientHelloSpec::new()
+        .set_cipher_suites(vec![
+            GREASE_PLACEHOLDER, // TLS Grease
+            TLS_AES_128_GCM_SHA256,
+            TLS_AES_256_GCM_SHA384,
+            TLS_CHACHA20_POLY1305_SHA256,
+            TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256,
+            TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256,
+            TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384,
+            TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384,
+            TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305,
+            TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305,
+            TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA,
+            TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA,
+            TLS_RSA_WITH_AES_128_GCM_SHA256,
+            TLS_RSA_WITH_AES_256_GCM_SHA384,
+            TLS_RSA_WITH_AES_128_CBC_SHA,
+            TLS_RSA_WITH_AES_256_CBC_SHA,
+        ])
+        // Chrome shuffles the extensions for each connection
+        .set_extensions_with_shuffle(
+            vec![
+                TlsExtension::TLSGrease,
+                TlsExtension::ServerName,
+                TlsExtension::ExtendedMasterSecret,
+                TlsExtension::RenegotiationInfo(1, vec![]),
+                TlsExtension::SupportedCurves(curves),
+                TlsExtension::SupportedPoints(vec![0x00]),
+                TlsExtension::SessionTicket,
+                TlsExtension::ALPN(vec!["h2".to_string(), "http/1.1".to_string()]),
+                TlsExtension::StatusRequest,
+                TlsExtension::SignatureAlgorithms(vec![
+                    SignatureScheme::ECDSAWithP256AndSHA256,
+                    SignatureScheme::PSSWithSHA256,
+                    SignatureScheme::PKCS1WithSHA256,
+                    SignatureScheme::ECDSAWithP384AndSHA384,
+                    SignatureScheme::PSSWithSHA384,
+                    SignatureScheme::PKCS1WithSHA384,
+                    SignatureScheme::PSSWithSHA512,
+                    SignatureScheme::PKCS1WithSHA512,
+                ]),
+                TlsExtension::SCT,
+                TlsExtension::KeyShare(key_shares),
+                TlsExtension::PSKModes(vec![1]),
+                TlsExtension::SupportedVersions(vec![
+                    GREASE_PLACEHOLDER,
+                    TlsVersion::VersionTLS13 as u16,
+                    TlsVersion::VersionTLS12 as u16,
+                ]),
+                TlsExtension::UtlsExtensionCompressCertificate(vec![0x0002]),
+                TlsExtension::UtlsExtensionApplicationSettings(vec!["h2".to_string()]),
+                TlsExtension::UtlsExtensionECH,
+                TlsExtension::TLSGrease,
+                TlsExtension::UtlsExtensionPadding,
+            ],
+            ShuffleMode::PerConnection { fixed: Vec::new() },
+        );
+
+    let (sec_ch_ua, user_agent) = match brand {
+        Chromium::Chrome => (
+            format!(r#""Chromium";v="{version}", "Google Chrome";v="{version}", "Not-A.Brand";v="99""#),
+            format!(
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/{version}.0.0.0 Safari/537.36"
+            ),
+        ),
+        Chromium::Edge => (
+            format!(r#""Chromium";v="{version}", "Microsoft Edge";v="{version}", "Not-A.Brand";v="99""#),
+            format!(
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/{version}.0.0.0 Safari/537.36 Edg/{version}.0.0.0"
+            ),
+        ),
+    };
+
+    let mut common_headers = vec![
+        ("sec-ch-ua", sec_ch_ua),
+        ("sec-ch-ua-mobile", "?0".to_string()),
+        ("sec-ch-ua-platform", r#""Windows""#.to_string()),
+        ("upgrade-insecure-requests", "1".to_string()),
+        ("user-agent", user_agent),
+        ("accept", "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8,application/signed-exchange;v=b3;q=0.7".to_string()),
+        ("sec-fetch-site", "none".to_string()),
+        ("sec-fetch-mode", "navigate".to_string()),
+        ("sec-fetch-user", "?1".to_string()),
+        ("sec-fetch-dest", "document".to_string()),
+        ("accept-encoding", "gzip, deflate, br, zstd".to_string()),
+        ("accept-language", "en-US,en;q=0.9".to_string()),
+    ];
+    if profile >= 124 {
+        common_headers.push(("priority", "u=0, i".to_string()));
+    }
+
+    ImpersonationConfig {
+        utls_config: UTlsConfig {
+            id: ClientHelloId::Custom,
+            spec: Some(spec),
+        },
+        http2_settings_frame: Http2Settings::builder()
+            .header_table_size(65536)
+            .enable_push(0)
+            .initial_window_size(6291456)
+            .max_header_list_size(262144)
+            .build()
+            .expect("valid http2 settings"),
+        http2_connection_flow: 15663105,
+        common_pseudo_header_order: pseudo_header_order(":method,:authority,:scheme,:path"),
+        common_header_order: header_order(&[
+            "content-length",
+            "pragma",
+            "cache-control",
+            "sec-ch-ua",
+            "sec-ch-ua-mobile",
+            "sec-ch-ua-platform",
+            "upgrade-insecure-requests",
+            "origin",
+            "content-type",
+            "user-agent",
+            "accept",
+            "sec-fetch-site",
+            "sec-fetch-mode",
+            "sec-fetch-user",
+            "sec-fetch-dest",
+            "referer",
+            "accept-encoding",
+            "accept-language",
+            "cookie",
+            "priority",
+        ]),
+        common_headers: header_map(common_headers),
+        http2_header_priority: Http2PriorityParam {
+            stream_dep: 0,
+            exclusive: true,
+            weight: 255,
+        },
+        http2_priority_frames: Vec::new(),
+        request_kind_headers: kind_headers(
+            [
+                (RequestKind::Fetch, Some("*/*"), "cors", "empty", "u=1, i"),
+                (RequestKind::Script, Some("*/*"), "no-cors", "script", "u=1"),
+                (
+                    RequestKind::Style,
+                    Some("text/css,*/*;q=0.1"),
+                    "no-cors",
+                    "style",
+                    "u=0",
+                ),
+                (
+                    RequestKind::Image,
+                    Some("image/avif,image/webp,image/apng,image/svg+xml,image/*,*/*;q=0.8"),
+                    "no-cors",
+                    "image",
+                    "i",
+                ),
+                (RequestKind::Font, Some("*/*"), "cors", "font", "u=0"),
+                (RequestKind::WebSocket, None, "websocket", "websocket", ""),
+            ],
+            true,
+            profile >= 124,
+        ),
+    }
+}
+
+// === Apple ===
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Apple {
+    MacOS,
+    Ios,
+}
+
+fn apple(version: u16, platform: Apple) -> ImpersonationConfig {
+    let version = version.max(SAFARI_VERSIONS[0]);
+    let profile = snap(SAFARI_VERSIONS, version);
+
+    let spec = ClientHelloSpec::new()
+        .set_cipher_suites(vec![
+            GREASE_PLACEHOLDER, // TLS Grease
+            TLS_AES_128_GCM_SHA256,
+            TLS_AES_256_GCM_SHA384,
+            TLS_CHACHA20_POLY1305_SHA256,
+            TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384,
+            TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256,
+            TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305,
+            TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384,
+            TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256,
+            TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305,
+            TLS_ECDHE_ECDSA_WITH_AES_256_CBC_SHA,
+            TLS_ECDHE_ECDSA_WITH_AES_128_CBC_SHA,
+            TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA,
+            TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA,
+            TLS_RSA_WITH_AES_256_GCM_SHA384,
+            TLS_RSA_WITH_AES_128_GCM_SHA256,
+            TLS_RSA_WITH_AES_256_CBC_SHA,
+            TLS_RSA_WITH_AES_128_CBC_SHA,
+            0xc008, // TLS_ECDHE_ECDSA_WITH_3DES_EDE_CBC_SHA
+            TLS_ECDHE_RSA_WITH_3DES_EDE_CBC_SHA,
+            TLS_RSA_WITH_3DES_EDE_CBC_SHA,
+        ])
+        .set_extensions(
+            false,
+            vec![
+                TlsExtension::TLSGrease,
+                TlsExtension::ServerName,
+                TlsExtension::ExtendedMasterSecret,
+                TlsExtension::RenegotiationInfo(1, vec![]),
+                TlsExtension::SupportedCurves(vec![
+                    CurveID::Grease,
+                    CurveID::X25519,
+                    CurveID::CurveP256,
+                    CurveID::CurveP384,
+                    CurveID::CurveP521,
+                ]),
+                TlsExtension::SupportedPoints(vec![0x00]),
+                TlsExtension::ALPN(vec!["h2".to_string(), "http/1.1".to_string()]),
+                TlsExtension::StatusRequest,
+                TlsExtension::SignatureAlgorithms(vec![
+                    SignatureScheme::ECDSAWithP256AndSHA256,
+                    SignatureScheme::PSSWithSHA256,
+                    SignatureScheme::PKCS1WithSHA256,
+                    SignatureScheme::ECDSAWithP384AndSHA384,
+                    SignatureScheme::ECDSAWithSHA1,
+                    SignatureScheme::PSSWithSHA384,
+                    SignatureScheme::PSSWithSHA384,
+                    SignatureScheme::PKCS1WithSHA384,
+                    SignatureScheme::PSSWithSHA512,
+                    SignatureScheme::PKCS1WithSHA512,
+                    SignatureScheme::PKCS1WithSHA1,
+                ]),
+                TlsExtension::SCT,
+                TlsExtension::KeyShare(vec![
+                    (CurveID::Grease, vec![0x00]),
+                    (CurveID::X25519, vec![]),
+                ]),
+                TlsExtension::PSKModes(vec![1]),
+                TlsExtension::SupportedVersions(vec![
+                    GREASE_PLACEHOLDER,
+                    TlsVersion::VersionTLS13 as u16,
+                    TlsVersion::VersionTLS12 as u16,
+                    TlsVersion::VersionTLS11 as u16,
+                    TlsVersion::VersionTLS10 as u16,
+                ]),
+                TlsExtension::UtlsExtensionCompressCertificate(vec![0x0001]),
+                TlsExtension::TLSGrease,
+                TlsExtension::UtlsExtensionPadding,
+            ],
+        )
+        .set_tls_version_min(TlsVersion::VersionTLS10);
+
+    let user_agent = match platform {
+        Apple::MacOS => format!(
+            "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/{version}.0 Safari/605.1.15"
+        ),
+        Apple::Ios => format!(
+            "Mozilla/5.0 (iPhone; CPU iPhone OS {version}_0 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/{version}.0 Mobile/15E148 Safari/604.1"
+        ),
+    };
+
+    let mut common_headers = vec![
+        (
+            "accept",
+            "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8".to_string(),
+        ),
+        ("user-agent", user_agent),
+        ("accept-language", "en-US,en;q=0.9".to_string()),
+        ("accept-encoding", "gzip, deflate, br".to_string()),
+    ];
+    // Fetch metadata headers are sent since Safari 16.4
+    if profile >= 17 {
+        common_headers.extend([
+            ("sec-fetch-site", "none".to_string()),
+            ("sec-fetch-mode", "navigate".to_string()),
+            ("sec-fetch-dest", "document".to_string()),
+        ]);
+    }
+    if profile >= 18 {
+        common_headers.push(("priority", "u=0, i".to_string()));
+    }
+
+    let (http2_settings_frame, http2_connection_flow, pseudo) = match profile {
+        18.. => (
+            Http2Settings::builder()
+                .enable_push(0)
+                .max_concurrent_streams(100)
+                .initial_window_size(2097152)
+                .enable_connect_protocol(1)
+                .no_rfc7540_priorities(1)
+                .build()
+                .expect("valid http2 settings"),
+            10420225,
+            ":method,:scheme,:authority,:path",
+        ),
+        17 => (
+            Http2Settings::builder()
+                .enable_push(0)
+                .initial_window_size(4194304)
+                .max_concurrent_streams(100)
+                .build()
+                .expect("valid http2 settings"),
+            10485760,
+            ":method,:scheme,:path,:authority",
+        ),
+        _ => (
+            Http2Settings::builder()
+                .initial_window_size(4194304)
+                .max_concurrent_streams(100)
+                .build()
+                .expect("valid http2 settings"),
+            10485760,
+            ":method,:scheme,:path,:authority",
+        ),
+    };
+
+    ImpersonationConfig {
+        utls_config: UTlsConfig {
+            id: ClientHelloId::Custom,
+            spec: Some(spec),
+        },
+        http2_settings_frame,
+        http2_connection_flow,
+        common_pseudo_header_order: pseudo_header_order(pseudo),
+        common_header_order: header_order(&[
+            "content-type",
+            "origin",
+            "sec-fetch-dest",
+            "user-agent",
+            "accept",
+            "sec-fetch-site",
+            "sec-fetch-mode",
+            "referer",
+            "content-length",
+            "accept-language",
+            "priority",
+            "accept-encoding",
+            "cookie",
+        ]),
+        common_headers: header_map(common_headers),
+        http2_header_priority: Http2PriorityParam {
+            stream_dep: 0,
+            exclusive: false,
+            weight: 254,
+        },
+        http2_priority_frames: Vec::new(),
+        request_kind_headers: kind_headers(
+            [
+                (RequestKind::Fetch, Some("*/*"), "cors", "empty", "u=3, i"),
+                (RequestKind::Script, Some("*/*"), "no-cors", "script", "u=2"),
+                (
+                    RequestKind::Style,
+                    Some("text/css,*/*;q=0.1"),
+                    "no-cors",
+                    "style",
+                    "u=2",
+                ),
+                (
+                    RequestKind::Image,
+                    Some("image/webp,image/avif,image/jxl,image/heic,image/heic-sequence,video/*;q=0.8,image/png,image/svg+xml,image/*;q=0.8,*/*;q=0.5"),
+                    "no-cors",
+                    "image",
+                    "u=5, i",
+                ),
+                (RequestKind::Font, Some("*/*"), "cors", "font", "u=3"),
+                (RequestKind::WebSocket, None, "websocket", "websocket", ""),
+            ],
+            profile >= 17,
+            profile >= 18,
+        ),
+    }
+}
+
+// === Helpers ===
+
+/// Nearest recorded version at or below the given one, or the oldest recorded one
+fn snap(versions: &[u16], version: u16) -> u16 {
+    versions
+        .iter()
+        .rev()
+        .find(|&&v| v <= version)
+        .copied()
+        .unwrap_or(versions[0])
+}
+
+fn pseudo_header_order(order: &str) -> Vec<String> {
+    order.split(',').map(str::to_string).collect()
+}
+
+fn header_order(order: &[&str]) -> Vec<String> {
+    order.iter().map(|h| h.to_string()).collect()
+}
+
+fn header_map(headers: Vec<(&'static str, String)>) -> HeaderMap {
+    let mut map = HeaderMap::with_capacity(headers.len());
+    for (k, v) in headers {
+        map.insert(
+            HeaderName::from_static(k),
+            HeaderValue::try_from(v).expect("valid header value"),
+        );
+    }
+    map
+}
+
+/// Headers of each subresource kind as `(kind, accept, sec-fetch-mode, sec-fetch-dest,
+/// priority)`, assuming a same-origin request. Empty priority is never sent.
+fn kind_headers(
+    kinds: [(RequestKind, Option<&str>, &str, &str, &str); 6],
+    fetch_metadata: bool,
+    priority: bool,
+) -> BTreeMap<RequestKind, HeaderMap> {
+    kinds
+        .into_iter()
+        .map(|(kind, accept, mode, dest, kind_priority)| {
+            let mut headers = Vec::new();
+            if let Some(accept) = accept {
+                headers.push(("accept", accept.to_string()));
+            }
+            if fetch_metadata {
+                headers.extend([
+                    ("sec-fetch-site", "same-origin".to_string()),
+                    ("sec-fetch-mode", mode.to_string()),
+                    ("sec-fetch-dest", dest.to_string()),
+                ]);
+            }
+            if priority && !kind_priority.is_empty() {
+                headers.push(("priority", kind_priority.to_string()));
+            }
+            (kind, header_map(headers))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_profiles() -> Vec<(String, ImpersonationConfig)> {
+        let mut profiles = Vec::new();
+        for &v in CHROME_VERSIONS {
+            profiles.push((format!("chrome {v}"), chrome(v)));
+            profiles.push((format!("edge {v}"), edge(v)));
+        }
+        for &v in FIREFOX_VERSIONS {
+            profiles.push((format!("firefox {v}"), firefox(v)));
+        }
+        for &v in SAFARI_VERSIONS {
+            profiles.push((format!("safari {v}"), safari(v)));
+            profiles.push((format!("ios {v}"), ios(v)));
+        }
+        for &v in OKHTTP_VERSIONS {
+            profiles.push((format!("okhttp {v}"), okhttp(v)));
+        }
+        profiles
+    }
+
+    #[test]
+    fn test_profiles_consistent() {
+        for (name, config) in all_profiles() {
+            crate::client::builder::validate_impersonation_config(&config)
+                .unwrap_or_else(|e| panic!("{name}: {e}"));
+
+            let kind_headers = config.request_kind_headers.values();
+            for key in config
+                .common_headers
+                .keys()
+                .chain(kind_headers.flat_map(|h| h.keys()))
+            {
+                assert!(
+                    config.common_header_order.iter().any(|h| h == key.as_str()),
+                    "{name}: `{key}` missing in common_header_order"
+                );
+            }
+            for (kind, headers) in &config.request_kind_headers {
+                assert_ne!(*kind, RequestKind::Document, "{name}");
+                for key in headers.keys() {
+                    assert!(
+                        RequestKind::HEADERS.contains(&key.as_str()),
+                        "{name}: `{key}` of {kind:?} is not kind dependent"
+                    );
+                }
+            }
+
+            let diagnostics = config.validate();
+            assert!(diagnostics.is_empty(), "{name}: {diagnostics:?}");
+        }
+    }
+
+    #[test]
+    fn test_version_snapping() {
+        let user_agent = |config: &ImpersonationConfig| {
+            config.common_headers["user-agent"]
+                .to_str()
+                .unwrap()
+                .to_string()
+        };
+        let curves = |config: &ImpersonationConfig| {
+            let spec = config.utls_config.spec.as_ref().unwrap();
+            spec.extensions
+                .iter()
+                .find_map(|ext| match ext {
+                    TlsExtension::SupportedCurves(curves) => {
+                        Some(curves.iter().map(CurveID::id).collect::<Vec<_>>())
+                    }
+                    _ => None,
+                })
+                .unwrap()
+        };
+
+        let config = chrome(126);
+        assert!(user_agent(&config).contains("Chrome/126.0.0.0"));
+        assert!(config.common_headers["sec-ch-ua"]
+            .to_str()
+            .unwrap()
+            .contains(r#""Google Chrome";v="126""#));
+        assert!(curves(&config).contains(&X25519_KYBER768));
+        assert!(!curves(&chrome(120)).contains(&X25519_KYBER768));
+        assert!(curves(&chrome(131)).contains(&X25519_MLKEM768));
+
+        assert!(user_agent(&edge(124)).contains("Edg/124.0.0.0"));
+        assert!(user_agent(&chrome(100)).contains(&format!("Chrome/{}.0.0.0", CHROME_VERSIONS[0])));
+        assert!(user_agent(&firefox(125)).contains("Firefox/125.0"));
+        assert!(user_agent(&ios(17)).contains("iPhone OS 17_0"));
+    }
+
+    #[test]
+    fn test_from_client_hello_id() {
+        let config =
+            from_client_hello_id(&ClientHelloId::Chrome("106_Shuffle".to_string())).unwrap();
+        assert!(config.common_headers["user-agent"]
+            .to_str()
+            .unwrap()
+            .contains("Chrome/"));
+        assert!(from_client_hello_id(&ClientHelloId::Firefox("120".to_string())).is_some());
+        assert!(from_client_hello_id(&ClientHelloId::Safari("16_0".to_string())).is_some());
+        assert!(from_client_hello_id(&ClientHelloId::Android11Okhttp("11".to_string())).is_some());
+        assert!(from_client_hello_id(&ClientHelloId::Chrome("auto".to_string())).is_none());
+        assert!(from_client_hello_id(&ClientHelloId::Custom).is_none());
+    }
+}
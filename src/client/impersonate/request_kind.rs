@@ -0,0 +1,54 @@
+use http::HeaderMap;
+
+use super::ImpersonationConfig;
+
+/// Destination of a request, browsers send different `accept`, fetch metadata and
+/// `priority` headers for each of them.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RequestKind {
+    /// Top level navigation, described by `common_headers`. Default one.
+    #[default]
+    Document,
+    /// `fetch()` or `XMLHttpRequest`
+    Fetch,
+    Script,
+    Style,
+    Image,
+    Font,
+    /// Opening handshake of a WebSocket
+    WebSocket,
+}
+
+impl RequestKind {
+    pub const ALL: [Self; 7] = [
+        Self::Document,
+        Self::Fetch,
+        Self::Script,
+        Self::Style,
+        Self::Image,
+        Self::Font,
+        Self::WebSocket,
+    ];
+
+    /// Headers of `common_headers` depending on the request kind, they are replaced as a
+    /// whole by the headers of the kind.
+    pub const HEADERS: [&'static str; 7] = [
+        "accept",
+        "upgrade-insecure-requests",
+        "sec-fetch-site",
+        "sec-fetch-mode",
+        "sec-fetch-user",
+        "sec-fetch-dest",
+        "priority",
+    ];
+}
+
+impl ImpersonationConfig {
+    /// Headers sent for the given kind of request instead of the kind dependent ones of
+    /// `common_headers`, `None` if `common_headers` are sent as is.
+    #[inline]
+    pub fn kind_headers(&self, kind: RequestKind) -> Option<&HeaderMap> {
+        self.request_kind_headers.get(&kind)
+    }
+}
@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+
+use super::Http2PriorityParam;
+
+/// Remote definition of [`Http2PriorityParam`], which is shared with Go side
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Http2PriorityParam")]
+pub(super) struct Http2PriorityParamDef {
+    stream_dep: u32,
+    exclusive: bool,
+    weight: u8,
+}
+
+/// [`HeaderMap`](http::HeaderMap) as a map from lowercase header name to its value, or
+/// to a list of values if the header is sent multiple times.
+pub(super) mod header_map {
+    use std::fmt;
+
+    use http::{HeaderMap, HeaderName, HeaderValue};
+    use serde::{
+        de::{Error as _, MapAccess, Visitor},
+        ser::{Error as _, SerializeMap},
+        Deserializer, Serializer,
+    };
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum Values {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    pub(in super::super) fn serialize<S: Serializer>(
+        headers: &HeaderMap,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(headers.keys_len()))?;
+        for name in headers.keys() {
+            let mut values = headers
+                .get_all(name)
+                .iter()
+                .map(|v| v.to_str().map(str::to_string).map_err(S::Error::custom))
+                .collect::<Result<Vec<_>, _>>()?;
+            let values = if values.len() == 1 {
+                Values::One(values.remove(0))
+            } else {
+                Values::Many(values)
+            };
+            map.serialize_entry(name.as_str(), &values)?;
+        }
+        map.end()
+    }
+
+    pub(in super::super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HeaderMap, D::Error> {
+        deserializer.deserialize_map(HeaderMapVisitor)
+    }
+
+    /// Visit the entries one by one, so that the order in config file is kept
+    struct HeaderMapVisitor;
+
+    impl<'de> Visitor<'de> for HeaderMapVisitor {
+        type Value = HeaderMap;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a map from header name to value or list of values")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut headers = HeaderMap::with_capacity(map.size_hint().unwrap_or_default());
+            while let Some((name, values)) = map.next_entry::<String, Values>()? {
+                let name = HeaderName::try_from(name).map_err(A::Error::custom)?;
+                let values = match values {
+                    Values::One(value) => vec![value],
+                    Values::Many(values) => values,
+                };
+                for value in values {
+                    let value = HeaderValue::try_from(value).map_err(A::Error::custom)?;
+                    headers.append(name.clone(), value);
+                }
+            }
+            Ok(headers)
+        }
+    }
+}
+
+/// Headers of each [`RequestKind`](super::RequestKind), in the format of [`header_map`]
+pub(super) mod kind_headers {
+    use std::collections::BTreeMap;
+
+    use http::HeaderMap;
+    use serde::{Deserializer, Serializer};
+
+    use super::*;
+    use crate::client::impersonate::RequestKind;
+
+    struct HeadersRef<'a>(&'a HeaderMap);
+
+    impl Serialize for HeadersRef<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            header_map::serialize(self.0, serializer)
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct Headers(#[serde(with = "header_map")] HeaderMap);
+
+    pub(in super::super) fn serialize<S: Serializer>(
+        headers: &BTreeMap<RequestKind, HeaderMap>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(headers.iter().map(|(kind, h)| (kind, HeadersRef(h))))
+    }
+
+    pub(in super::super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<RequestKind, HeaderMap>, D::Error> {
+        let headers = BTreeMap::<RequestKind, Headers>::deserialize(deserializer)?;
+        Ok(headers.into_iter().map(|(kind, h)| (kind, h.0)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{profiles, ImpersonationConfig};
+
+    fn all_profiles() -> Vec<ImpersonationConfig> {
+        vec![
+            profiles::chrome(131),
+            profiles::edge(124),
+            profiles::firefox(133),
+            profiles::safari(18),
+            profiles::ios(17),
+            profiles::okhttp(4),
+        ]
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        for config in all_profiles() {
+            let json = serde_json::to_string(&config).unwrap();
+            let decoded: ImpersonationConfig = serde_json::from_str(&json).unwrap();
+            assert_eq!(serde_json::to_string(&decoded).unwrap(), json);
+            assert_eq!(decoded.akamai_fingerprint(), config.akamai_fingerprint());
+            assert_eq!(
+                decoded.utls_config.spec.unwrap().ja4(),
+                config.utls_config.spec.unwrap().ja4()
+            );
+        }
+    }
+
+    #[test]
+    fn test_toml_round_trip() {
+        for config in all_profiles() {
+            let text = toml::to_string(&config).unwrap();
+            let decoded: ImpersonationConfig = toml::from_str(&text).unwrap();
+
+            // TOML tables are not ordered, order of headers is kept by `common_header_order`
+            assert_eq!(decoded.common_headers, config.common_headers);
+            assert_eq!(decoded.common_header_order, config.common_header_order);
+            assert_eq!(decoded.request_kind_headers, config.request_kind_headers);
+            assert_eq!(decoded.akamai_fingerprint(), config.akamai_fingerprint());
+            assert_eq!(
+                decoded.utls_config.spec.unwrap().ja3(),
+                config.utls_config.spec.unwrap().ja3()
+            );
+        }
+    }
+
+    #[test]
+    fn test_deserialize_checks() {
+        let mut value = serde_json::to_value(profiles::chrome(131)).unwrap();
+        value["http2_settings_frame"] = serde_json::json!([
+            { "id": "InitialWindowSize", "value": 6291456 },
+            { "id": { "Unknown": 4 }, "value": 65536 },
+        ]);
+        let err = serde_json::from_value::<ImpersonationConfig>(value.clone()).unwrap_err();
+        assert!(err.to_string().contains("duplicated"), "{err}");
+
+        value["http2_settings_frame"] = serde_json::json!([]);
+        value["common_headers"] = serde_json::json!({ "accept": ["a", "b"], "bad name": "x" });
+        assert!(serde_json::from_value::<ImpersonationConfig>(value.clone()).is_err());
+
+        value["common_headers"] = serde_json::json!({ "accept": ["a", "b"], "dnt": "1" });
+        let config: ImpersonationConfig = serde_json::from_value(value).unwrap();
+        assert_eq!(config.common_headers.get_all("accept").iter().count(), 2);
+        assert_eq!(config.common_headers["dnt"], "1");
+    }
+}
@@ -0,0 +1,252 @@
+use super::*;
+
+/// TLS 1.3 cipher suites, see RFC 8446, Appendix B.4
+const TLS13_CIPHER_SUITES: [u16; 5] = [0x1301, 0x1302, 0x1303, 0x1304, 0x1305];
+/// Type of pre_shared_key extension
+const EXT_PRE_SHARED_KEY: u16 = 41;
+
+/// How bad a [`SpecDiagnostic`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The handshake fails, or the ClientHello is rejected by a conforming server.
+    Error,
+    /// The handshake may succeed, but the fingerprint is unlike any real client.
+    Warning,
+}
+
+/// Problem found by [`ClientHelloSpec::validate`] or [`ImpersonationConfig::validate`]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SpecDiagnostic {
+    #[error("extension {0} is present more than once")]
+    DuplicateExtension(u16),
+    #[error("key share group {0} is not in supported_groups")]
+    KeyShareGroupNotSupported(u16),
+    #[error(
+        "supported_versions contains {0:#06x}, which is out of tls_version_min and tls_version_max"
+    )]
+    SupportedVersionOutOfRange(u16),
+    #[error("tls_version_max is TLS 1.3 but supported_versions extension is missing")]
+    MissingSupportedVersions,
+    #[error("TLS 1.3 cipher suite {0:#06x} is offered but TLS 1.3 is not enabled")]
+    Tls13CipherWithoutTls13(u16),
+    #[error("pre_shared_key extension must be the last one")]
+    PreSharedKeyNotLast,
+    #[error("fixed GREASE value {0:#06x} is not a GREASE value")]
+    InvalidGreaseValue(u16),
+    #[error("ALPN offers h2 but no HTTP2 settings are configured")]
+    AlpnH2WithoutHttp2Settings,
+    #[error("HTTP2 settings are configured but ALPN does not offer h2")]
+    Http2SettingsWithoutAlpnH2,
+}
+
+impl SpecDiagnostic {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::DuplicateExtension(_)
+            | Self::KeyShareGroupNotSupported(_)
+            | Self::SupportedVersionOutOfRange(_)
+            | Self::PreSharedKeyNotLast
+            | Self::InvalidGreaseValue(_) => Severity::Error,
+            Self::MissingSupportedVersions
+            | Self::Tls13CipherWithoutTls13(_)
+            | Self::AlpnH2WithoutHttp2Settings
+            | Self::Http2SettingsWithoutAlpnH2 => Severity::Warning,
+        }
+    }
+
+    #[inline]
+    pub fn is_error(&self) -> bool {
+        self.severity() == Severity::Error
+    }
+}
+
+impl ClientHelloSpec {
+    /// Check the spec before it's sent to Go side, an empty list means nothing is wrong.
+    pub fn validate(&self) -> Vec<SpecDiagnostic> {
+        let mut diagnostics = Vec::new();
+
+        let mut seen = Vec::with_capacity(self.extensions.len());
+        for ext_type in self.extensions.iter().map(TlsExtension::ext_type) {
+            if is_grease(ext_type) {
+                continue;
+            }
+            if !seen.contains(&ext_type) {
+                seen.push(ext_type);
+            } else if !diagnostics.contains(&SpecDiagnostic::DuplicateExtension(ext_type)) {
+                diagnostics.push(SpecDiagnostic::DuplicateExtension(ext_type));
+            }
+        }
+
+        let curves: Vec<u16> = self.supported_curves().map(CurveID::id).collect();
+        for ext in &self.extensions {
+            if let TlsExtension::KeyShare(shares) = ext {
+                for (group, _) in shares {
+                    let id = group.id();
+                    let supported = if is_grease(id) {
+                        curves.iter().any(|&c| is_grease(c))
+                    } else {
+                        curves.contains(&id)
+                    };
+                    if !supported {
+                        diagnostics.push(SpecDiagnostic::KeyShareGroupNotSupported(id));
+                    }
+                }
+            }
+        }
+
+        let min = self.tls_version_min.clone() as u16;
+        let max = self.tls_version_max.clone() as u16;
+        let supported_versions = self.extensions.iter().find_map(|ext| match ext {
+            TlsExtension::SupportedVersions(versions) => Some(versions),
+            _ => None,
+        });
+        match supported_versions {
+            Some(versions) => diagnostics.extend(
+                versions
+                    .iter()
+                    .filter(|&&v| !is_grease(v) && !(min..=max).contains(&v))
+                    .map(|&v| SpecDiagnostic::SupportedVersionOutOfRange(v)),
+            ),
+            None if max >= TlsVersion::VersionTLS13 as u16 => {
+                diagnostics.push(SpecDiagnostic::MissingSupportedVersions)
+            }
+            None => {}
+        }
+
+        if max < TlsVersion::VersionTLS13 as u16 {
+            diagnostics.extend(
+                self.cipher_suites
+                    .iter()
+                    .filter(|c| TLS13_CIPHER_SUITES.contains(c))
+                    .map(|&c| SpecDiagnostic::Tls13CipherWithoutTls13(c)),
+            );
+        }
+
+        if let Some(idx) = self
+            .extensions
+            .iter()
+            .position(|ext| ext.ext_type() == EXT_PRE_SHARED_KEY)
+        {
+            if idx != self.extensions.len() - 1 {
+                diagnostics.push(SpecDiagnostic::PreSharedKeyNotLast);
+            }
+        }
+
+        if let GreasePolicy::Fixed(value) = self.grease {
+            if !is_grease(value) {
+                diagnostics.push(SpecDiagnostic::InvalidGreaseValue(value));
+            }
+        }
+
+        diagnostics
+    }
+
+    /// Whether ALPN extension offers h2
+    pub(crate) fn offers_h2(&self) -> bool {
+        self.extensions.iter().any(|ext| {
+            matches!(ext, TlsExtension::ALPN(protocols) if protocols.iter().any(|p| p == "h2"))
+        })
+    }
+
+    /// Whether ECH extension is present, which carries real ECH if configured
+    pub(crate) fn offers_ech(&self) -> bool {
+        self.extensions
+            .iter()
+            .any(|ext| matches!(ext, TlsExtension::UtlsExtensionECH))
+    }
+}
+
+impl ImpersonationConfig {
+    /// Check the spec like [`ClientHelloSpec::validate`], and whether ALPN agrees with
+    /// the HTTP2 settings.
+    pub fn validate(&self) -> Vec<SpecDiagnostic> {
+        let Some(spec) = &self.utls_config.spec else {
+            return Vec::new();
+        };
+
+        let mut diagnostics = spec.validate();
+        match (spec.offers_h2(), self.http2_settings_frame.is_empty()) {
+            (true, true) => diagnostics.push(SpecDiagnostic::AlpnH2WithoutHttp2Settings),
+            (false, false) => diagnostics.push(SpecDiagnostic::Http2SettingsWithoutAlpnH2),
+            _ => {}
+        }
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let spec = ClientHelloSpec::new()
+            .set_tls_version_max(TlsVersion::VersionTLS12)
+            .set_cipher_suites(vec![TLS_AES_128_GCM_SHA256, TLS_RSA_WITH_AES_128_CBC_SHA])
+            .set_extensions(
+                false,
+                vec![
+                    TlsExtension::TLSGrease,
+                    TlsExtension::ServerName,
+                    TlsExtension::FakeExtensionPreSharedKey,
+                    TlsExtension::SupportedCurves(vec![CurveID::X25519]),
+                    TlsExtension::KeyShare(vec![
+                        (CurveID::Grease, vec![0]),
+                        (CurveID::X25519, vec![]),
+                    ]),
+                    TlsExtension::SupportedVersions(vec![GREASE_PLACEHOLDER, 0x0304, 0x0303]),
+                    TlsExtension::ServerName,
+                    TlsExtension::ServerName,
+                    TlsExtension::TLSGrease,
+                ],
+            );
+
+        assert_eq!(
+            spec.validate(),
+            vec![
+                SpecDiagnostic::DuplicateExtension(0),
+                SpecDiagnostic::KeyShareGroupNotSupported(GREASE_PLACEHOLDER),
+                SpecDiagnostic::SupportedVersionOutOfRange(0x0304),
+                SpecDiagnostic::Tls13CipherWithoutTls13(TLS_AES_128_GCM_SHA256),
+                SpecDiagnostic::PreSharedKeyNotLast,
+            ]
+        );
+
+        let spec = ClientHelloSpec::new()
+            .set_extensions(false, vec![TlsExtension::SupportedVersions(vec![0x0304])])
+            .set_grease_policy(GreasePolicy::Fixed(0x0a0b));
+        assert_eq!(
+            spec.validate(),
+            vec![SpecDiagnostic::InvalidGreaseValue(0x0a0b)]
+        );
+
+        let spec = ClientHelloSpec::new();
+        assert_eq!(
+            spec.validate(),
+            vec![SpecDiagnostic::MissingSupportedVersions]
+        );
+        assert!(!spec.validate()[0].is_error());
+    }
+
+    #[test]
+    fn test_validate_alpn() {
+        let mut config = profiles::chrome(131);
+        assert!(config.validate().is_empty());
+
+        config.http2_settings_frame = Http2Settings::default();
+        assert_eq!(
+            config.validate(),
+            vec![SpecDiagnostic::AlpnH2WithoutHttp2Settings]
+        );
+
+        let mut config = profiles::chrome(131);
+        if let Some(spec) = &mut config.utls_config.spec {
+            spec.extensions
+                .retain(|ext| !matches!(ext, TlsExtension::ALPN(_)));
+        }
+        assert_eq!(
+            config.validate(),
+            vec![SpecDiagnostic::Http2SettingsWithoutAlpnH2]
+        );
+    }
+}
@@ -0,0 +1,565 @@
+use std::{
+    fmt::{Debug, Write as _},
+    net::IpAddr,
+    sync::RwLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use http::{HeaderValue, Uri};
+use publicsuffix::{List, Psl};
+
+use crate::error::ErrorType;
+
+/// Storage of cookies used by [`Client`](super::Client), see [`Jar`] for the default one.
+pub trait CookieStore: Debug + Send + Sync {
+    /// Store the cookies of `set-cookie` headers in a response from `url`
+    fn set_cookies(&self, url: &Uri, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>);
+
+    /// Value of `cookie` header for a request to `url`, `None` if no cookie is sent
+    fn cookies(&self, url: &Uri, context: SiteContext) -> Option<HeaderValue>;
+}
+
+/// Relation between a request and the page making it, deciding which SameSite cookies
+/// are sent
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SiteContext {
+    /// Same-site request, or one not made by a page, like typing the URL. Default one.
+    #[default]
+    SameSite,
+    /// Cross-site top-level navigation with a safe method, `Lax` cookies are sent too
+    CrossSiteNavigation,
+    /// Other cross-site requests, only `SameSite=None` cookies are sent
+    CrossSite,
+}
+
+/// `SameSite` attribute of a cookie
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+/// Cookie kept by [`Jar`]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredCookie {
+    pub name: String,
+    pub value: String,
+    /// Lowercase domain, without leading dot
+    pub domain: String,
+    /// Only sent to `domain` itself but not its subdomains, for cookies without `Domain`
+    pub host_only: bool,
+    pub path: String,
+    pub secure: bool,
+    pub http_only: bool,
+    /// `None` if not given, which is treated as `Lax` like Chrome does
+    pub same_site: Option<SameSite>,
+    /// Expiry in seconds since Unix epoch, `None` for session cookies
+    pub expires: Option<u64>,
+}
+
+impl StoredCookie {
+    #[inline]
+    fn is_expired(&self, now: u64) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    #[inline]
+    fn same_key(&self, other: &Self) -> bool {
+        self.name == other.name && self.domain == other.domain && self.path == other.path
+    }
+}
+
+/// Default [`CookieStore`] following RFC 6265, with the `SameSite` and cookie prefix rules
+/// of browsers.
+///
+/// Cookies can be saved to and loaded from Netscape `cookies.txt`, or JSON with `serde`
+/// feature, so that sessions survive restarts.
+#[derive(Debug, Default)]
+pub struct Jar {
+    cookies: RwLock<Vec<Entry>>,
+    public_suffix_list: List,
+}
+
+#[derive(Debug)]
+struct Entry {
+    cookie: StoredCookie,
+    /// Order of creation, older cookies are sent first among the ones of the same path
+    created: u64,
+}
+
+impl Jar {
+    /// Create an empty jar.
+    ///
+    /// Without [`Jar::with_public_suffix_list`], only top level domains are treated as
+    /// public suffixes when checking `Domain` attribute.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn with_public_suffix_list(mut self, list: List) -> Self {
+        self.public_suffix_list = list;
+        self
+    }
+
+    /// Store a cookie from `set-cookie` header value of a response from `url`, returns
+    /// whether it's accepted.
+    pub fn add_cookie_str(&self, set_cookie: &str, url: &Uri) -> bool {
+        match self.parse(set_cookie, url, now()) {
+            Some(cookie) => {
+                self.insert(cookie);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Store a cookie as is, replacing the one with the same name, domain and path.
+    ///
+    /// An expired cookie removes the one it replaces.
+    pub fn insert(&self, cookie: StoredCookie) {
+        let now = now();
+        let mut cookies = self.cookies.write().unwrap();
+        cookies.retain(|e| !e.cookie.is_expired(now));
+
+        let created = match cookies.iter().position(|e| e.cookie.same_key(&cookie)) {
+            Some(idx) => cookies.remove(idx).created,
+            None => cookies
+                .iter()
+                .map(|e| e.created + 1)
+                .max()
+                .unwrap_or_default(),
+        };
+        if !cookie.is_expired(now) {
+            cookies.push(Entry { cookie, created });
+        }
+    }
+
+    /// All cookies not expired yet, in the order of creation
+    pub fn stored_cookies(&self) -> Vec<StoredCookie> {
+        let now = now();
+        let cookies = self.cookies.read().unwrap();
+        let mut entries: Vec<_> = cookies
+            .iter()
+            .filter(|e| !e.cookie.is_expired(now))
+            .collect();
+        entries.sort_by_key(|e| e.created);
+        entries.into_iter().map(|e| e.cookie.clone()).collect()
+    }
+
+    pub fn clear(&self) {
+        self.cookies.write().unwrap().clear();
+    }
+
+    /// Export as Netscape `cookies.txt`, used by curl, wget and browser extensions.
+    ///
+    /// The format has no `SameSite`, and session cookies are written with expiry `0`.
+    pub fn to_netscape(&self) -> String {
+        let mut text = String::from("# Netscape HTTP Cookie File\n");
+        for cookie in self.stored_cookies() {
+            let _ = writeln!(
+                text,
+                "{}{}{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                if cookie.http_only { "#HttpOnly_" } else { "" },
+                if cookie.host_only { "" } else { "." },
+                cookie.domain,
+                netscape_bool(!cookie.host_only),
+                cookie.path,
+                netscape_bool(cookie.secure),
+                cookie.expires.unwrap_or_default(),
+                cookie.name,
+                cookie.value,
+            );
+        }
+        text
+    }
+
+    /// Import cookies from Netscape `cookies.txt`, expired ones are skipped.
+    ///
+    /// Nothing is imported if any line is invalid.
+    pub fn load_netscape(&self, text: &str) -> Result<(), ErrorType> {
+        let mut cookies = Vec::new();
+        for (idx, line) in text.lines().enumerate() {
+            let (http_only, line) = match line.strip_prefix("#HttpOnly_") {
+                Some(line) => (true, line),
+                None => (false, line),
+            };
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid =
+                |reason: &str| ErrorType::InvalidCookies(format!("line {}: {reason}", idx + 1));
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [domain, subdomains, path, secure, expires, name, value] = fields[..] else {
+                return Err(invalid("expect 7 fields separated by tab"));
+            };
+            let parse_bool = |field: &str| match field {
+                "TRUE" => Ok(true),
+                "FALSE" => Ok(false),
+                _ => Err(invalid("expect TRUE or FALSE")),
+            };
+            let expires: u64 = expires.parse().map_err(|_| invalid("invalid expiry"))?;
+            let host_only = !(parse_bool(subdomains)? || domain.starts_with('.'));
+
+            cookies.push(StoredCookie {
+                name: name.to_string(),
+                value: value.to_string(),
+                domain: domain.trim_start_matches('.').to_ascii_lowercase(),
+                host_only,
+                path: path.to_string(),
+                secure: parse_bool(secure)?,
+                http_only,
+                same_site: None,
+                expires: (expires != 0).then_some(expires),
+            });
+        }
+
+        cookies.into_iter().for_each(|cookie| self.insert(cookie));
+        Ok(())
+    }
+
+    /// Export as a JSON array of [`StoredCookie`], keeping every attribute
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.stored_cookies()).expect("cookies are serializable")
+    }
+
+    /// Import cookies exported by [`Jar::to_json`], expired ones are skipped
+    #[cfg(feature = "serde")]
+    pub fn load_json(&self, json: &str) -> Result<(), ErrorType> {
+        let cookies: Vec<StoredCookie> =
+            serde_json::from_str(json).map_err(|e| ErrorType::InvalidCookies(e.to_string()))?;
+        cookies.into_iter().for_each(|cookie| self.insert(cookie));
+        Ok(())
+    }
+
+    /// Parse a `set-cookie` header value per RFC 6265, Section 5.3. `None` if it should be
+    /// ignored.
+    fn parse(&self, set_cookie: &str, url: &Uri, now: u64) -> Option<StoredCookie> {
+        let cookie = cookie::Cookie::parse(set_cookie).ok()?;
+        let host = request_host(url)?;
+        let secure_origin = is_secure_origin(url, &host);
+
+        let domain = cookie
+            .domain()
+            .map(|d| d.trim_end_matches('.').to_ascii_lowercase())
+            .filter(|d| !d.is_empty());
+        let (domain, host_only) = match domain {
+            // A public suffix can only be set by itself, as a host-only cookie
+            Some(domain) if self.is_public_suffix(&domain) => {
+                if domain != host {
+                    return None;
+                }
+                (host, true)
+            }
+            Some(domain) if domain_match(&host, &domain) => (domain, false),
+            Some(_) => return None,
+            None => (host, true),
+        };
+
+        let path = match cookie.path() {
+            Some(path) if path.starts_with('/') => path.to_string(),
+            _ => default_path(url.path()),
+        };
+
+        let secure = cookie.secure().unwrap_or_default();
+        if secure && !secure_origin {
+            return None;
+        }
+
+        let same_site = cookie.same_site().map(|s| match s {
+            cookie::SameSite::Strict => SameSite::Strict,
+            cookie::SameSite::Lax => SameSite::Lax,
+            cookie::SameSite::None => SameSite::None,
+        });
+        if same_site == Some(SameSite::None) && !secure {
+            return None;
+        }
+
+        let name = cookie.name();
+        if name.starts_with("__Secure-") && !secure {
+            return None;
+        }
+        if name.starts_with("__Host-") && !(secure && host_only && path == "/") {
+            return None;
+        }
+
+        // Max-Age takes precedence over Expires
+        let expires = match (cookie.max_age(), cookie.expires_datetime()) {
+            (Some(max_age), _) => Some(now.saturating_add_signed(max_age.whole_seconds())),
+            (None, Some(expires)) => Some(u64::try_from(expires.unix_timestamp()).unwrap_or(0)),
+            (None, None) => None,
+        };
+
+        Some(StoredCookie {
+            name: name.to_string(),
+            value: cookie.value().to_string(),
+            domain,
+            host_only,
+            path,
+            secure,
+            http_only: cookie.http_only().unwrap_or_default(),
+            same_site,
+            expires,
+        })
+    }
+
+    fn is_public_suffix(&self, domain: &str) -> bool {
+        self.public_suffix_list
+            .suffix(domain.as_bytes())
+            .is_some_and(|suffix| suffix == domain.as_bytes())
+    }
+}
+
+impl CookieStore for Jar {
+    fn set_cookies(&self, url: &Uri, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>) {
+        let now = now();
+        for header in cookie_headers {
+            if let Some(cookie) = header
+                .to_str()
+                .ok()
+                .and_then(|header| self.parse(header, url, now))
+            {
+                self.insert(cookie);
+            }
+        }
+    }
+
+    fn cookies(&self, url: &Uri, context: SiteContext) -> Option<HeaderValue> {
+        let host = request_host(url)?;
+        let secure_origin = is_secure_origin(url, &host);
+        let path = match url.path() {
+            "" => "/",
+            path => path,
+        };
+        let now = now();
+
+        let cookies = self.cookies.read().unwrap();
+        let mut matched: Vec<&Entry> = cookies
+            .iter()
+            .filter(|e| {
+                let c = &e.cookie;
+                let same_site = matches!(
+                    (c.same_site.unwrap_or(SameSite::Lax), context),
+                    (SameSite::None, _)
+                        | (_, SiteContext::SameSite)
+                        | (SameSite::Lax, SiteContext::CrossSiteNavigation)
+                );
+                let domain_ok = if c.host_only {
+                    c.domain == host
+                } else {
+                    domain_match(&host, &c.domain)
+                };
+                !c.is_expired(now)
+                    && domain_ok
+                    && path_match(path, &c.path)
+                    && (!c.secure || secure_origin)
+                    && same_site
+            })
+            .collect();
+        if matched.is_empty() {
+            return None;
+        }
+
+        // Longer paths first, then older ones first, see RFC 6265, Section 5.4
+        matched.sort_by(|a, b| {
+            b.cookie
+                .path
+                .len()
+                .cmp(&a.cookie.path.len())
+                .then(a.created.cmp(&b.created))
+        });
+        let value = matched
+            .iter()
+            .map(|e| format!("{}={}", e.cookie.name, e.cookie.value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        HeaderValue::try_from(value).ok()
+    }
+}
+
+#[inline]
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[inline]
+fn netscape_bool(value: bool) -> &'static str {
+    if value {
+        "TRUE"
+    } else {
+        "FALSE"
+    }
+}
+
+/// Lowercase host of the URL, without trailing dot
+fn request_host(url: &Uri) -> Option<String> {
+    let host = url.host()?.trim_end_matches('.');
+    (!host.is_empty()).then(|| host.to_ascii_lowercase())
+}
+
+/// `localhost` is treated as secure like browsers do
+#[inline]
+fn is_secure_origin(url: &Uri, host: &str) -> bool {
+    matches!(url.scheme_str(), Some("https" | "wss")) || host == "localhost"
+}
+
+#[inline]
+fn is_ip(host: &str) -> bool {
+    host.starts_with('[') || host.parse::<IpAddr>().is_ok()
+}
+
+/// Domain matching of RFC 6265, Section 5.1.3
+fn domain_match(host: &str, domain: &str) -> bool {
+    host == domain
+        || (!is_ip(host)
+            && host.len() > domain.len()
+            && host.ends_with(domain)
+            && host.as_bytes()[host.len() - domain.len() - 1] == b'.')
+}
+
+/// Path matching of RFC 6265, Section 5.1.4
+fn path_match(request_path: &str, cookie_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path.as_bytes()[cookie_path.len()] == b'/'))
+}
+
+/// Default path of RFC 6265, Section 5.1.4, the directory of the request path
+fn default_path(request_path: &str) -> String {
+    match request_path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(idx) => request_path[..idx].to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uri(s: &str) -> Uri {
+        s.parse().unwrap()
+    }
+
+    fn cookie_header(jar: &Jar, url: &str, context: SiteContext) -> Option<String> {
+        jar.cookies(&uri(url), context)
+            .map(|v| v.to_str().unwrap().to_string())
+    }
+
+    #[test]
+    fn test_jar() {
+        let jar = Jar::new();
+        let page = uri("https://www.example.com/account/login");
+        for (set_cookie, accepted) in [
+            ("sid=1; Path=/; Secure; HttpOnly", true),
+            ("theme=dark; Domain=.example.com; Path=/", true),
+            ("last=login", true),
+            ("strict=1; Path=/; SameSite=Strict", true),
+            ("tracker=1; Path=/; SameSite=None; Secure", true),
+            ("insecure=1; SameSite=None", false),
+            ("other=1; Domain=other.com", false),
+            ("tld=1; Domain=com", false),
+            ("__Host-id=1; Secure; Path=/", true),
+            ("__Host-bad=1; Secure; Path=/; Domain=example.com", false),
+            // accepted to remove the stored one
+            ("gone=1; Max-Age=0", true),
+        ] {
+            assert_eq!(
+                jar.add_cookie_str(set_cookie, &page),
+                accepted,
+                "{set_cookie}"
+            );
+        }
+        assert_eq!(jar.stored_cookies()[2].path, "/account");
+
+        assert_eq!(
+            cookie_header(
+                &jar,
+                "https://www.example.com/account/x",
+                SiteContext::SameSite
+            )
+            .unwrap(),
+            "last=login; sid=1; theme=dark; strict=1; tracker=1; __Host-id=1"
+        );
+        assert_eq!(
+            cookie_header(&jar, "http://cdn.example.com/", SiteContext::SameSite).unwrap(),
+            "theme=dark"
+        );
+        assert_eq!(
+            cookie_header(
+                &jar,
+                "https://www.example.com/",
+                SiteContext::CrossSiteNavigation
+            )
+            .unwrap(),
+            "sid=1; theme=dark; tracker=1; __Host-id=1"
+        );
+        assert_eq!(
+            cookie_header(&jar, "https://www.example.com/", SiteContext::CrossSite).unwrap(),
+            "tracker=1"
+        );
+        assert_eq!(
+            cookie_header(&jar, "https://example.org/", SiteContext::SameSite),
+            None
+        );
+
+        // replaced in place, then removed by an expired one
+        jar.add_cookie_str("sid=2; Path=/; Secure", &page);
+        assert_eq!(jar.stored_cookies()[0].value, "2");
+        jar.add_cookie_str(
+            "sid=2; Path=/; Secure; Expires=Thu, 01 Jan 1970 00:00:00 GMT",
+            &page,
+        );
+        assert!(jar.stored_cookies().iter().all(|c| c.name != "sid"));
+    }
+
+    #[test]
+    fn test_netscape() {
+        let jar = Jar::new();
+        let page = uri("https://www.example.com/");
+        jar.add_cookie_str("sid=1; Secure; HttpOnly; Max-Age=3600", &page);
+        jar.add_cookie_str("theme=dark; Domain=example.com; Path=/app", &page);
+
+        let text = jar.to_netscape();
+        let loaded = Jar::new();
+        loaded.load_netscape(&text).unwrap();
+        assert_eq!(loaded.to_netscape(), text);
+        assert!(text.contains("#HttpOnly_www.example.com\tFALSE\t/\tTRUE\t"));
+        assert!(text.contains(".example.com\tTRUE\t/app\tFALSE\t0\ttheme\tdark"));
+
+        for invalid in [
+            "example.com\tTRUE\t/\tFALSE\t0\tname",
+            "example.com\tYES\t/\tFALSE\t0\tname\tvalue",
+            "example.com\tTRUE\t/\tFALSE\tnever\tname\tvalue",
+        ] {
+            assert!(
+                matches!(
+                    Jar::new().load_netscape(invalid),
+                    Err(ErrorType::InvalidCookies(_))
+                ),
+                "{invalid} should be rejected"
+            );
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json() {
+        let jar = Jar::new();
+        let page = uri("https://www.example.com/");
+        jar.add_cookie_str("sid=1; Secure; SameSite=Strict; Max-Age=3600", &page);
+        jar.add_cookie_str("theme=dark", &page);
+
+        let loaded = Jar::new();
+        loaded.load_json(&jar.to_json()).unwrap();
+        assert_eq!(loaded.stored_cookies(), jar.stored_cookies());
+        assert!(loaded.load_json("{}").is_err());
+    }
+}
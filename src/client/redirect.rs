@@ -0,0 +1,451 @@
+use std::{fmt, sync::Arc};
+
+use http::{
+    header::{
+        AUTHORIZATION, CONTENT_ENCODING, CONTENT_LANGUAGE, CONTENT_LENGTH, CONTENT_LOCATION,
+        CONTENT_TYPE, COOKIE, LOCATION, PROXY_AUTHORIZATION, REFERER,
+    },
+    HeaderValue, Method, Response, StatusCode, Uri,
+};
+use publicsuffix::List;
+
+use crate::{
+    client::session::{fetch_site, Origin, Referrer, ReferrerPolicy},
+    request::Request,
+};
+
+/// How redirects are followed, set on [`ClientBuilder`](super::ClientBuilder) and
+/// overridden by [`Request::redirect`].
+///
+/// Redirects are followed by Rust side, so that cookies are stored and sensitive headers
+/// are checked at each hop.
+#[derive(Clone)]
+pub enum RedirectPolicy {
+    /// Never follow, the redirect response is returned as is.
+    None,
+    /// Follow at most the given number of redirects, fail with
+    /// [`ErrorType::Redirect`](crate::error::ErrorType::Redirect) on more.
+    Limited(usize),
+    /// Decide each redirect with a closure.
+    Custom(Arc<dyn Fn(&RedirectAttempt<'_>) -> RedirectAction + Send + Sync>),
+}
+
+impl Default for RedirectPolicy {
+    /// At most 10 redirects like reqwest, browsers allow 20.
+    #[inline]
+    fn default() -> Self {
+        Self::Limited(10)
+    }
+}
+
+impl fmt::Debug for RedirectPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => f.write_str("None"),
+            Self::Limited(max) => f.debug_tuple("Limited").field(max).finish(),
+            Self::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+impl RedirectPolicy {
+    #[inline]
+    pub fn custom<F>(policy: F) -> Self
+    where
+        F: Fn(&RedirectAttempt<'_>) -> RedirectAction + Send + Sync + 'static,
+    {
+        Self::Custom(Arc::new(policy))
+    }
+
+    pub(crate) fn decide(&self, attempt: &RedirectAttempt<'_>) -> RedirectAction {
+        match self {
+            Self::None => RedirectAction::Stop,
+            Self::Limited(max) if attempt.previous.len() >= *max => {
+                RedirectAction::Error(format!("too many redirects, more than {max}"))
+            }
+            Self::Limited(_) => RedirectAction::Follow,
+            Self::Custom(policy) => policy(attempt),
+        }
+    }
+}
+
+/// A redirect to be decided by [`RedirectPolicy::Custom`]
+#[derive(Debug)]
+pub struct RedirectAttempt<'a> {
+    /// Status of the redirect response
+    pub status: StatusCode,
+    /// URL to be requested next, resolved from `location` header
+    pub next: &'a Uri,
+    /// Redirects already followed, starting from the original request
+    pub previous: &'a [RedirectHop],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RedirectAction {
+    Follow,
+    /// Return the redirect response as is
+    Stop,
+    /// Fail the request with the given reason
+    Error(String),
+}
+
+/// A redirect that has been followed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectHop {
+    /// URL which responded with the redirect
+    pub url: Uri,
+    pub status: StatusCode,
+}
+
+/// Redirects followed before the response, found in its extensions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectHistory {
+    /// Redirects in the order they are followed, empty if there is none
+    pub hops: Vec<RedirectHop>,
+    /// URL of the final response
+    pub final_url: Uri,
+}
+
+/// Next URL if the response is a redirect to be followed, resolved against `url`
+pub(crate) fn redirect_location<B>(response: &Response<B>, url: &Uri) -> Option<Uri> {
+    if !matches!(response.status().as_u16(), 301 | 302 | 303 | 307 | 308) {
+        return None;
+    }
+    let location = response.headers().get(LOCATION)?.to_str().ok()?;
+    resolve_location(url, location)
+}
+
+/// Rewrite the request for the next hop, see <https://fetch.spec.whatwg.org/#http-redirect-fetch>.
+/// Returns whether the method and body are kept.
+///
+/// The method is changed to GET without body for 303, and for POST on 301 and 302.
+/// Credentials are stripped when leaving the origin, and `referer` is computed again for the
+/// next URL like browsers do. `cookie` header set by the request itself is kept only within
+/// the origin, while the one from cookie store is refreshed for each hop. `sec-fetch-site` other than `none` is downgraded to the relation
+/// between the hops once it's less trusted, so that a redirect through another site makes
+/// the request cross-site like browsers do.
+pub(crate) fn rewrite_request(
+    request: &mut Request,
+    status: StatusCode,
+    next: Uri,
+    user_cookie: bool,
+    list: &List,
+) -> bool {
+    let to_get = match status.as_u16() {
+        301 | 302 => request.method == Method::POST,
+        303 => !matches!(request.method, Method::GET | Method::HEAD),
+        _ => false,
+    };
+    if to_get {
+        request.method = Method::GET;
+        request.body = None;
+        for name in [
+            CONTENT_ENCODING,
+            CONTENT_LANGUAGE,
+            CONTENT_LOCATION,
+            CONTENT_TYPE,
+            CONTENT_LENGTH,
+        ] {
+            request.headers.remove(name);
+        }
+    }
+
+    let cross_origin = Origin::of(&request.uri) != Origin::of(&next);
+    if cross_origin {
+        request.headers.remove(AUTHORIZATION);
+        request.headers.remove(PROXY_AUTHORIZATION);
+    }
+    if let Some(referrer) = referrer(request) {
+        match referrer.policy.referer(&referrer.initiator, &next) {
+            Some(referer) => request.headers.insert(REFERER, referer),
+            None => request.headers.remove(REFERER),
+        };
+    }
+    if let Some(site) = request.headers.get(SEC_FETCH_SITE) {
+        let hop = fetch_site(list, Some(&request.uri), &next);
+        if site != "none" && site_rank(hop) > site.to_str().map_or(0, site_rank) {
+            request
+                .headers
+                .insert(SEC_FETCH_SITE, HeaderValue::from_static(hop));
+        }
+    }
+    if cross_origin || !user_cookie {
+        request.headers.remove(COOKIE);
+    }
+
+    request.uri = next;
+    !to_get
+}
+
+const SEC_FETCH_SITE: &str = "sec-fetch-site";
+
+/// Where `referer` comes from, set by [`BrowserSession`](super::BrowserSession) or taken
+/// from the header of the first hop with the default policy
+fn referrer(request: &mut Request) -> Option<Referrer> {
+    if let Some(referrer) = request.extensions.get::<Referrer>() {
+        return Some(referrer.clone());
+    }
+    let initiator: Uri = request.headers.get(REFERER)?.to_str().ok()?.parse().ok()?;
+    let referrer = Referrer {
+        initiator,
+        policy: ReferrerPolicy::default(),
+    };
+    request.extensions.insert(referrer.clone());
+    Some(referrer)
+}
+
+/// How far a `sec-fetch-site` value is from the initiator
+fn site_rank(site: &str) -> u8 {
+    match site {
+        "same-origin" => 0,
+        "same-site" => 1,
+        _ => 2,
+    }
+}
+
+/// Resolve the reference in `location` header against the URL, see RFC 3986, Section 5.2
+fn resolve_location(base: &Uri, location: &str) -> Option<Uri> {
+    let location = location.trim();
+    let location = location.split_once('#').map_or(location, |(l, _)| l);
+
+    let has_scheme = location.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    });
+    if has_scheme {
+        return location.parse().ok();
+    }
+
+    let scheme = base.scheme_str()?;
+    if location.starts_with("//") {
+        return format!("{scheme}:{location}").parse().ok();
+    }
+
+    let authority = base.authority()?.as_str();
+    let (path, query) = match location.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (location, None),
+    };
+    let path = if path.is_empty() {
+        base.path().to_string()
+    } else if path.starts_with('/') {
+        remove_dot_segments(path)
+    } else {
+        let base_path = base.path();
+        let dir = &base_path[..base_path.rfind('/').map_or(0, |idx| idx + 1)];
+        remove_dot_segments(&format!("/{}{path}", dir.trim_start_matches('/')))
+    };
+    let query = match (query, location.is_empty()) {
+        (Some(query), _) => Some(query),
+        // empty reference keeps the query of base
+        (None, true) => base.query(),
+        (None, false) => None,
+    };
+
+    let target = match query {
+        Some(query) => format!("{scheme}://{authority}{path}?{query}"),
+        None => format!("{scheme}://{authority}{path}"),
+    };
+    target.parse().ok()
+}
+
+/// Remove `.` and `..` segments of an absolute path, see RFC 3986, Section 5.2.4
+fn remove_dot_segments(path: &str) -> String {
+    let segments: Vec<&str> = path.split('/').skip(1).collect();
+    let mut output = Vec::with_capacity(segments.len());
+    for (idx, segment) in segments.iter().enumerate() {
+        let is_last = idx + 1 == segments.len();
+        match *segment {
+            "." => {}
+            ".." => {
+                output.pop();
+            }
+            segment => {
+                output.push(segment);
+                continue;
+            }
+        }
+        // `/a/..` and `/a/.` are directories
+        if is_last {
+            output.push("");
+        }
+    }
+    format!("/{}", output.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn test_resolve_location() {
+        let base: Uri = "https://example.com/a/b/c?x=1".parse().unwrap();
+        for (location, expected) in [
+            ("https://other.org/p", "https://other.org/p"),
+            ("//cdn.example.com/x", "https://cdn.example.com/x"),
+            ("/login?next=%2F#top", "https://example.com/login?next=%2F"),
+            ("d", "https://example.com/a/b/d"),
+            ("../d/./e", "https://example.com/a/d/e"),
+            ("..", "https://example.com/a/"),
+            ("?y=2", "https://example.com/a/b/c?y=2"),
+            ("", "https://example.com/a/b/c?x=1"),
+        ] {
+            assert_eq!(
+                resolve_location(&base, location).unwrap().to_string(),
+                expected,
+                "{location}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_rewrite_request() {
+        let mut request =
+            Request::post("https://example.com/form".parse().unwrap()).set_body("a=1");
+        request
+            .headers
+            .insert(CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+        request
+            .headers
+            .insert(AUTHORIZATION, HeaderValue::from_static("Bearer x"));
+        request
+            .headers
+            .insert(COOKIE, HeaderValue::from_static("sid=1"));
+
+        let mut kept = Request::post("https://example.com/form".parse().unwrap()).set_body("a=1");
+        assert!(rewrite_request(
+            &mut kept,
+            StatusCode::TEMPORARY_REDIRECT,
+            "https://example.com/next".parse().unwrap(),
+            true,
+            &List::new(),
+        ));
+        assert_eq!(kept.method, Method::POST);
+        assert!(kept.body.is_some());
+
+        assert!(!rewrite_request(
+            &mut request,
+            StatusCode::FOUND,
+            "https://example.com/done".parse().unwrap(),
+            true,
+            &List::new(),
+        ));
+        assert_eq!(request.method, Method::GET);
+        assert!(request.body.is_none());
+        assert!(!request.headers.contains_key(CONTENT_TYPE));
+        assert!(request.headers.contains_key(AUTHORIZATION));
+        assert!(request.headers.contains_key(COOKIE));
+
+        let _ = rewrite_request(
+            &mut request,
+            StatusCode::MOVED_PERMANENTLY,
+            "https://other.org/".parse().unwrap(),
+            true,
+            &List::new(),
+        );
+        assert!(!request.headers.contains_key(AUTHORIZATION));
+        assert!(!request.headers.contains_key(COOKIE));
+        assert_eq!(request.uri, "https://other.org/");
+    }
+
+    #[test]
+    fn test_rewrite_cross_origin() {
+        let list = List::new();
+        let mut request = Request::get("https://www.example.com/a?secret=1".parse().unwrap());
+        request.headers.insert(
+            REFERER,
+            HeaderValue::from_static("https://www.example.com/login?token=1"),
+        );
+        request
+            .headers
+            .insert(SEC_FETCH_SITE, HeaderValue::from_static("same-origin"));
+        let mut navigation = request.clone_without_body();
+        navigation
+            .headers
+            .insert(SEC_FETCH_SITE, HeaderValue::from_static("none"));
+        navigation.extensions.insert(Referrer {
+            initiator: "https://www.example.com/login".parse().unwrap(),
+            policy: ReferrerPolicy::NoReferrer,
+        });
+
+        let _ = rewrite_request(
+            &mut request,
+            StatusCode::FOUND,
+            "https://static.example.com/".parse().unwrap(),
+            false,
+            &list,
+        );
+        // origin only by strict-origin-when-cross-origin
+        assert_eq!(request.headers[REFERER], "https://www.example.com/");
+        assert_eq!(request.headers[SEC_FETCH_SITE], "same-site");
+
+        let _ = rewrite_request(
+            &mut request,
+            StatusCode::FOUND,
+            "https://other.org/".parse().unwrap(),
+            false,
+            &list,
+        );
+        assert_eq!(request.headers[REFERER], "https://www.example.com/");
+        assert_eq!(request.headers[SEC_FETCH_SITE], "cross-site");
+
+        // back to the initiator, but it has been through another site
+        let _ = rewrite_request(
+            &mut request,
+            StatusCode::FOUND,
+            "https://www.example.com/".parse().unwrap(),
+            false,
+            &list,
+        );
+        assert_eq!(
+            request.headers[REFERER],
+            "https://www.example.com/login?token=1"
+        );
+        assert_eq!(request.headers[SEC_FETCH_SITE], "cross-site");
+
+        // user initiated navigations stay `none`
+        let _ = rewrite_request(
+            &mut navigation,
+            StatusCode::FOUND,
+            "https://other.org/".parse().unwrap(),
+            false,
+            &list,
+        );
+        assert_eq!(navigation.headers[SEC_FETCH_SITE], "none");
+        assert!(!navigation.headers.contains_key(REFERER));
+    }
+
+    #[test]
+    fn test_redirect_policy() {
+        let next: Uri = "https://example.com/".parse().unwrap();
+        let hop = RedirectHop {
+            url: next.clone(),
+            status: StatusCode::FOUND,
+        };
+        let previous = vec![hop; 2];
+        let attempt = RedirectAttempt {
+            status: StatusCode::FOUND,
+            next: &next,
+            previous: &previous,
+        };
+
+        assert_eq!(RedirectPolicy::None.decide(&attempt), RedirectAction::Stop);
+        assert_eq!(
+            RedirectPolicy::Limited(2).decide(&attempt),
+            RedirectAction::Error("too many redirects, more than 2".to_string())
+        );
+        assert_eq!(
+            RedirectPolicy::Limited(3).decide(&attempt),
+            RedirectAction::Follow
+        );
+        let same_host = RedirectPolicy::custom(|attempt| match attempt.next.host() {
+            Some("example.com") => RedirectAction::Follow,
+            _ => RedirectAction::Stop,
+        });
+        assert_eq!(same_host.decide(&attempt), RedirectAction::Follow);
+    }
+}
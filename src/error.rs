@@ -1,9 +1,33 @@
+pub mod fingerprint_error;
 pub mod go_error;
 
 #[derive(Debug, thiserror::Error)]
 pub enum ErrorType {
-    #[error("Unsupported HTTP method")]
-    UnsupportedHttpMethod,
+    #[error("Invalid proxy: {0}")]
+    InvalidProxy(String),
+    #[error("Invalid client config: {0}")]
+    InvalidConfig(String),
+    #[error("Request timeout")]
+    Timeout,
+    #[error("ECH config lookup failed: {0}")]
+    EchLookup(String),
+    #[error("Invalid cookies: {0}")]
+    InvalidCookies(String),
+    #[error("Redirect error: {0}")]
+    Redirect(String),
     #[error(transparent)]
     GoError(#[from] go_error::GoError),
+    #[error(transparent)]
+    Fingerprint(#[from] fingerprint_error::FingerprintError),
+}
+
+impl ErrorType {
+    /// Convert error code and message returned by Go side
+    pub(crate) fn from_go(code: i32, message: String) -> Self {
+        match code {
+            // context.DeadlineExceeded or net.Error with Timeout() == true
+            -1_000_030 => Self::Timeout,
+            _ => go_error::GoError::from((code, message)).into(),
+        }
+    }
 }
@@ -0,0 +1,13 @@
+#[derive(Debug, thiserror::Error)]
+pub enum FingerprintError {
+    #[error("FingerprintError: Invalid JA3 string: {0}")]
+    InvalidJa3(String),
+    #[error("FingerprintError: TLS extension {0} can not be expressed by TlsExtension")]
+    UnsupportedExtension(u16),
+    #[error("FingerprintError: Invalid Akamai HTTP2 fingerprint: {0}")]
+    InvalidAkamai(String),
+    #[error("FingerprintError: Invalid ClientHello: {0}")]
+    InvalidClientHello(String),
+    #[error("FingerprintError: Invalid capture file: {0}")]
+    InvalidCapture(String),
+}
@@ -4,6 +4,10 @@ pub enum GoError {
     ClientNotInitialized,
     #[error("GoError: Invalid request method")]
     InvalidRequestMethod,
+    #[error("GoError: Invalid proxy: {0}")]
+    InvalidProxy(String),
+    #[error("GoError: Invalid impersonation template: {0}")]
+    InvalidTemplate(String),
     #[error("GoError: update_impersonation_config: unknown extension type {0}")]
     InvalidTlsExtension(String),
     #[error("GoError: Unknown: {0}")]
@@ -15,8 +19,35 @@ impl From<(i32, String)> for GoError {
         match code {
             -1_000_000 => GoError::ClientNotInitialized,
             -1_000_010 => GoError::InvalidRequestMethod,
+            -1_000_020 => GoError::InvalidProxy(message),
+            -1_001_000 => GoError::InvalidTemplate(message),
             -1_001_001 => GoError::InvalidTlsExtension(message),
             _ => GoError::Unknown(message),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_code() {
+        assert!(matches!(
+            GoError::from((-1_000_020, "bad proxy".to_string())),
+            GoError::InvalidProxy(m) if m == "bad proxy"
+        ));
+        assert!(matches!(
+            GoError::from((-1_001_000, "bad template".to_string())),
+            GoError::InvalidTemplate(m) if m == "bad template"
+        ));
+        assert!(matches!(
+            GoError::from((-1_001_001, "1234".to_string())),
+            GoError::InvalidTlsExtension(m) if m == "1234"
+        ));
+        assert!(matches!(
+            GoError::from((-42, "oops".to_string())),
+            GoError::Unknown(m) if m == "oops"
+        ));
+    }
+}
@@ -5,8 +5,6 @@ pub mod binding {
     rust2go::r2g_include_binding!();
 }
 
-use crate::error::go_error::GoError;
-
 macro_rules! impl_go_result {
     ($name:ident) => {
         impl $name {
@@ -14,7 +12,7 @@ macro_rules! impl_go_result {
                 if self.code == 0 {
                     Ok(())
                 } else {
-                    Err(GoError::from((self.code, self.message)).into())
+                    Err(crate::error::ErrorType::from_go(self.code, self.message))
                 }
             }
         }
@@ -25,7 +23,7 @@ macro_rules! impl_go_result {
                 if self.code == 0 {
                     Ok(self.data.into())
                 } else {
-                    Err(GoError::from((self.code, self.message)).into())
+                    Err(crate::error::ErrorType::from_go(self.code, self.message))
                 }
             }
         }
@@ -34,9 +32,14 @@ macro_rules! impl_go_result {
 
 // === ReqwestxGoInit ===
 
+/// All functions return a [`GoResultFfi`]-like struct, non-zero code will be converted
+/// into [`GoError`](crate::error::go_error::GoError) by `into_result`, e.g. bad proxy
+/// syntax, unknown template or TLS extension.
 #[rust2go::r2g]
 pub trait ReqwestxGoInit {
-    /// Prepare the client, you should call this function before any other functions
+    /// Create a new client on Go side, returns the handle id of it.
+    ///
+    /// Every client owns its own config, and should be released by `drop_client`.
     ///
     /// # Pre-defined template
     /// - Chrome 0, will use utls.HelloChrome_106_Shuffle
@@ -45,26 +48,34 @@ pub trait ReqwestxGoInit {
     /// - Android11 Okhttp 10, will use utls.HelloAndroid_11_OkHttp, TODO: not implemented
     /// - Custom 255, will use utls.HelloCustom, TODO: not implemented
     ///
-    /// If you want to use your own template, you can use `set_client_hello_id` to set the ClientHelloID,
-    /// and provide any necessary setting
+    /// If you want to use your own template, you can use `update_impersonation_config`
+    /// to provide any necessary setting
+    #[send]
+    #[drop_safe]
+    fn new_client(config: ClientConfigFfi) -> GoResultClientFfi;
+
+    /// Release the client, the handle id should not be used anymore after calling this.
+    ///
+    /// Rust side keeps the client until every body of it is read to the end or closed by
+    /// `close_body`, so no body is open when it's called.
     #[send]
     #[drop_safe]
-    fn init_client(config: ClientConfigFfi) -> GoResultFfi;
+    fn drop_client(client_id: u64) -> GoResultFfi;
 
     /// Allow insecure connection
     #[send]
     #[drop_safe]
-    fn client_allow_insecure(data: bool) -> GoResultFfi;
+    fn client_allow_insecure(client_id: u64, data: bool) -> GoResultFfi;
 
     /// Set proxy
     #[send]
     #[drop_safe]
-    fn set_proxy_ffi(proxy: String) -> GoResultFfi;
+    fn set_proxy_ffi(client_id: u64, proxy: String) -> GoResultFfi;
 
     /// Manually set client impersonation config for adv usage
     #[send]
     #[drop_safe]
-    fn update_impersonation_config(config: ImpersonationConfigFfi) -> GoResultFfi;
+    fn update_impersonation_config(client_id: u64, config: ImpersonationConfigFfi) -> GoResultFfi;
 
     /// Manually GC
     fn force_gc(data: bool) -> GoResultFfi;
@@ -79,7 +90,18 @@ pub struct GoResultFfi {
 
 impl_go_result!(GoResultFfi);
 
-// === usage: init_client ===
+#[derive(Debug, rust2go::R2G)]
+#[repr(C)]
+pub struct GoResultClientFfi {
+    code: i32,
+    message: String,
+    /// Handle id of the client
+    data: u64,
+}
+
+impl_go_result!(GoResultClientFfi, u64);
+
+// === usage: new_client ===
 
 #[derive(Debug, rust2go::R2G)]
 #[repr(C)]
@@ -102,6 +124,19 @@ pub struct ClientConfigFfi {
     /// - Firefox 1, will use utls.HelloFirefox_105
     /// - Safari 2, will use utls.HelloSafari_16_0
     pub impersonation_template: u8,
+
+    /// Timeout of the whole request in milliseconds, 0 for no timeout
+    pub timeout_ms: u64,
+
+    /// Timeout of establishing the connection in milliseconds, 0 for no timeout
+    pub connect_timeout_ms: u64,
+
+    /// Timeout of each read from the connection in milliseconds, 0 for no timeout
+    pub read_timeout_ms: u64,
+
+    /// Whether Go side follows redirects itself. Always false, as redirects are followed
+    /// by Rust side with `RedirectPolicy`.
+    pub follow_redirects: bool,
 }
 
 impl Default for ClientConfigFfi {
@@ -111,6 +146,10 @@ impl Default for ClientConfigFfi {
             allow_insecure: false,
             proxy: "direct".to_string(),
             impersonation_template: 0xff,
+            timeout_ms: 0,
+            connect_timeout_ms: 0,
+            read_timeout_ms: 0,
+            follow_redirects: false,
         }
     }
 }
@@ -134,6 +173,8 @@ pub struct ImpersonationConfigFfi {
     pub common_headers: Vec<HttpHeaderFfi>,
     /// HTTP2 Header Priority, for HTTP2 fingerprint fmpersonation
     pub http2_header_priority: Http2PriorityParamFfi,
+    /// PRIORITY frames sent on each new HTTP2 connection, in order
+    pub http2_priority_frames: Vec<Http2PriorityFrameFfi>,
 }
 
 // === UTLS Config ===
@@ -160,6 +201,15 @@ pub struct ClientHelloSpecFfi {
     pub extensions: Vec<TlsExtensionFfi>,
     pub tls_version_min: u16,
     pub tls_version_max: u16,
+    /// Shuffle the extensions for each new TLS connection, like Chrome 106+
+    pub shuffle_per_connection: bool,
+    /// Extension types kept in place when shuffling, besides GREASE, padding and
+    /// pre_shared_key
+    pub shuffle_fixed_extensions: Vec<u16>,
+    /// How GREASE values are picked, 0 for `grease_value`, 1 for a random value per
+    /// connection, 2 for a random value per slot and connection
+    pub grease_policy: u8,
+    pub grease_value: u16,
 }
 
 #[derive(Debug, Default, rust2go::R2G)]
@@ -173,18 +223,20 @@ pub struct TlsExtensionFfi {
     pub vec_string: Vec<String>,
     pub data_isize: isize,
     pub data_bool: bool,
+    /// Write `vec_u8` as the extension data as is, ignoring the builtin one of `ext_type`
+    pub is_raw: bool,
 }
 
 // === HTTP2 Fingerprint Config ===
 
-#[derive(Debug, rust2go::R2G)]
+#[derive(Debug, Clone, rust2go::R2G)]
 #[repr(C)]
 pub struct Http2SettingFfi {
     pub setting_id: u16,
     pub setting_val: u32,
 }
 
-#[derive(Debug, rust2go::R2G)]
+#[derive(Debug, Clone, rust2go::R2G)]
 #[repr(C)]
 pub struct Http2PriorityParamFfi {
     pub stream_dep: u32,
@@ -192,6 +244,13 @@ pub struct Http2PriorityParamFfi {
     pub weight: u8,
 }
 
+#[derive(Debug, Clone, rust2go::R2G)]
+#[repr(C)]
+pub struct Http2PriorityFrameFfi {
+    pub stream_id: u32,
+    pub priority: Http2PriorityParamFfi,
+}
+
 // === ReqwestxGo ===
 
 #[rust2go::r2g]
@@ -199,15 +258,64 @@ pub trait ReqwestxGo {
     #[send]
     #[drop_safe]
     fn send(req: HttpRequestFfi) -> impl std::future::Future<Output = GoResultHttpResponseFfi>;
+
+    /// Cancel the in-flight request through its context, do nothing if already done
+    fn cancel(request_id: u64) -> GoResultFfi;
+
+    /// Read next chunk of the response body.
+    ///
+    /// Go side will release the body once EOF is reached or any error occurs.
+    #[send]
+    #[drop_safe]
+    fn read_body(body_id: u64) -> impl std::future::Future<Output = GoResultBodyChunkFfi>;
+
+    /// Release the response body which has not been read to the end
+    fn close_body(body_id: u64) -> GoResultFfi;
+
+    /// Write next chunk of the streaming request body, returns once Go side consumed it.
+    #[send]
+    #[drop_safe]
+    fn write_body(
+        request_id: u64,
+        chunk: Vec<u8>,
+    ) -> impl std::future::Future<Output = GoResultFfi>;
+
+    /// Finish the streaming request body, empty error for EOF, otherwise the request
+    /// will fail with the given error
+    fn close_upload(request_id: u64, error: String) -> GoResultFfi;
 }
 
 #[derive(Debug, rust2go::R2G)]
 #[repr(C)]
 pub struct HttpRequestFfi {
+    /// Handle id of the client to send the request with
+    pub client_id: u64,
+    /// Unique id of the request, for cancellation
+    pub request_id: u64,
+    /// Timeout of the whole request in milliseconds, 0 for client's default one
+    pub timeout_ms: u64,
     pub url: String,
-    pub method: u8,
+    /// Any valid HTTP method, checked by Go side
+    pub method: String,
+    /// In-memory body, ignored if `streaming_body` is true
     pub body: Vec<u8>,
+    /// Whether the body is streamed by `write_body` and `close_upload`
+    pub streaming_body: bool,
+    /// Content length of the body, -1 if unknown and chunked transfer-encoding is used
+    pub content_length: i64,
     pub headers: Vec<HttpHeaderFfi>,
+    /// ECHConfigList to offer, empty for GREASE ECH. A rejection does not fail the request,
+    /// Go side reports it with `ech_accepted` of the response.
+    pub ech_config_list: Vec<u8>,
+    /// Whether `priority` overrides the HEADERS priority of the impersonation config
+    pub has_priority: bool,
+    pub priority: Http2PriorityParamFfi,
+    /// Pseudo header order of this request, empty for the impersonation config's one
+    pub pseudo_header_order: Vec<String>,
+    /// Header order of this request, empty for the impersonation config's one
+    pub header_order: Vec<String>,
+    /// Common headers of the impersonation config not to be sent with this request
+    pub omitted_common_headers: Vec<String>,
 }
 
 #[derive(Debug, rust2go::R2G)]
@@ -228,8 +336,15 @@ pub struct HttpResponseFfi {
     pub proto_minor: isize,
     /// Headers
     pub headers: Vec<HttpHeaderFfi>,
-    /// Response data
-    pub data: Vec<u8>,
+    /// Id of the response body kept by Go side, read by `read_body`
+    pub body_id: u64,
+    /// Content length, -1 if unknown
+    pub content_length: i64,
+    /// Whether the server accepted ECH on the connection
+    pub ech_accepted: bool,
+    /// GREASE value of each slot used on the connection, 0 for unused slots, empty if
+    /// not over TLS
+    pub grease_values: Vec<u16>,
 }
 
 #[derive(Debug, rust2go::R2G)]
@@ -240,4 +355,26 @@ pub struct GoResultHttpResponseFfi {
     data: HttpResponseFfi,
 }
 
-impl_go_result!(GoResultHttpResponseFfi, http::Response<Vec<u8>>);
+impl_go_result!(
+    GoResultHttpResponseFfi,
+    http::Response<crate::response::Body>
+);
+
+#[derive(Debug, rust2go::R2G)]
+#[repr(C)]
+pub struct BodyChunkFfi {
+    /// Chunk data, may be empty
+    pub data: Vec<u8>,
+    /// Whether it's the last chunk
+    pub eof: bool,
+}
+
+#[derive(Debug, rust2go::R2G)]
+#[repr(C)]
+pub struct GoResultBodyChunkFfi {
+    code: i32,
+    message: String,
+    data: BodyChunkFfi,
+}
+
+impl_go_result!(GoResultBodyChunkFfi, BodyChunkFfi);
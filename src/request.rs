@@ -1,11 +1,23 @@
-use http::{Extensions, HeaderMap, HeaderValue, Method, Response, Uri};
+mod body;
+
+pub use body::Body;
+
+use std::time::Duration;
+
+use http::{header::COOKIE, Extensions, HeaderMap, HeaderName, HeaderValue, Method, Uri};
 
 use crate::{
+    client::{
+        builder::{duration_to_ms, validate_header_order, validate_pseudo_header_order},
+        impersonate::{ExtensiblePriority, Http2PriorityParam, ImpersonationConfig, RequestKind},
+        jar::{CookieStore, SiteContext},
+        redirect::RedirectPolicy,
+    },
     error::ErrorType,
-    ffi::{HttpHeaderFfi, HttpRequestFfi, ReqwestxGo, ReqwestxGoImpl},
+    ffi::{HttpHeaderFfi, HttpRequestFfi},
 };
 
-/// Wrapper for HTTP request
+/// Wrapper for HTTP request, sent by [`Client::execute`](crate::client::Client::execute)
 pub struct Request {
     /// The request's method
     pub method: Method,
@@ -19,8 +31,32 @@ pub struct Request {
     /// The request's extensions
     pub extensions: Extensions,
 
-    /// limit body type to be Vec<u8> to match Go side
-    pub body: Option<Vec<u8>>,
+    /// The request's body, in memory or streamed to Go side
+    pub body: Option<Body>,
+
+    /// Timeout of the whole request, override the client's default one
+    pub timeout: Option<Duration>,
+
+    /// Priority of the HTTP2 HEADERS frame, override the one of impersonation config
+    pub priority: Option<Http2PriorityParam>,
+
+    /// RFC 9218 priority sent as the `priority` header, replacing any one set in `headers`
+    pub extensible_priority: Option<ExtensiblePriority>,
+
+    /// Pseudo header order, override the `common_pseudo_header_order` of impersonation config
+    pub pseudo_header_order: Option<Vec<String>>,
+
+    /// Header order, override the `common_header_order` of impersonation config
+    pub header_order: Option<Vec<String>>,
+
+    /// Kind of the request, selecting the `accept`, fetch metadata and `priority` headers
+    pub kind: RequestKind,
+
+    /// How redirects are followed, override the client's default one
+    pub redirect: Option<RedirectPolicy>,
+
+    /// Common headers of impersonation config not to be sent, set by the request kind
+    omitted_common_headers: Vec<String>,
 }
 
 impl Request {
@@ -32,6 +68,14 @@ impl Request {
             headers: HeaderMap::with_capacity(32),
             extensions: Extensions::default(),
             body: None,
+            timeout: None,
+            priority: None,
+            extensible_priority: None,
+            pseudo_header_order: None,
+            header_order: None,
+            kind: RequestKind::Document,
+            redirect: None,
+            omitted_common_headers: Vec::new(),
         }
     }
 
@@ -77,23 +121,194 @@ impl Request {
     }
 
     #[inline]
-    pub fn set_body(mut self, body: Option<Vec<u8>>) -> Self {
-        self.body = body;
+    pub fn set_body(mut self, body: impl Into<Body>) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    /// Set timeout of the whole request, including connecting and reading the response.
+    #[inline]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set priority of the HTTP2 HEADERS frame, e.g. to depend on another idle stream of
+    /// [`ImpersonationConfig::http2_priority_frames`](crate::client::impersonate::ImpersonationConfig)
+    /// than the navigation one.
+    #[inline]
+    pub fn priority(mut self, priority: Http2PriorityParam) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Set RFC 9218 priority of the request, like `u=1` of Chrome's script requests.
+    #[inline]
+    pub fn extensible_priority(mut self, priority: ExtensiblePriority) -> Self {
+        self.extensible_priority = Some(priority);
         self
     }
 
-    pub async fn execute(self) -> Result<Response<Vec<u8>>, ErrorType> {
-        let method = match self.method {
-            Method::GET => 0,
-            Method::POST => 1,
-            Method::PATCH => 2,
-            Method::DELETE => 3,
-            Method::PUT => 4,
-            Method::HEAD => 5,
-            Method::OPTIONS => 6,
-            _ => return Err(ErrorType::UnsupportedHttpMethod),
+    /// Set pseudo header order of this request, must be a permutation of `:method`,
+    /// `:authority`, `:scheme` and `:path`.
+    #[inline]
+    pub fn pseudo_header_order<I, S>(mut self, order: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.pseudo_header_order = Some(order.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Set header order of this request with lowercase header names, e.g. to place
+    /// `content-type` and `origin` like the browser does for fetch requests.
+    #[inline]
+    pub fn header_order<I, S>(mut self, order: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.header_order = Some(order.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Set kind of the request, e.g. [`RequestKind::Fetch`] for API calls made by scripts.
+    ///
+    /// Headers set on the request itself are always kept.
+    #[inline]
+    pub fn kind(mut self, kind: RequestKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Replace the kind dependent common headers with the ones of the request kind, if
+    /// the config has them
+    pub(crate) fn apply_kind_headers(&mut self, config: Option<&ImpersonationConfig>) {
+        let Some(config) = config else {
+            return;
+        };
+        let Some(kind_headers) = config.kind_headers(self.kind) else {
+            return;
         };
 
+        // Applied again on each hop of redirects
+        self.omitted_common_headers.clear();
+
+        for name in RequestKind::HEADERS {
+            if self.headers.contains_key(name) {
+                continue;
+            }
+            let values = kind_headers.get_all(name);
+            if values.iter().next().is_some() {
+                for value in values {
+                    self.headers.append(name, value.clone());
+                }
+            } else if config.common_headers.contains_key(name) {
+                self.omitted_common_headers.push(name.to_string());
+            }
+        }
+    }
+
+    /// Set how redirects of this request are followed.
+    #[inline]
+    pub fn redirect(mut self, policy: RedirectPolicy) -> Self {
+        self.redirect = Some(policy);
+        self
+    }
+
+    /// Clone everything but the body, for the next hop of a redirect
+    pub(crate) fn clone_without_body(&self) -> Self {
+        Self {
+            method: self.method.clone(),
+            uri: self.uri.clone(),
+            headers: self.headers.clone(),
+            extensions: self.extensions.clone(),
+            body: None,
+            timeout: self.timeout,
+            priority: self.priority.clone(),
+            extensible_priority: self.extensible_priority,
+            pseudo_header_order: self.pseudo_header_order.clone(),
+            header_order: self.header_order.clone(),
+            kind: self.kind,
+            redirect: self.redirect.clone(),
+            omitted_common_headers: Vec::new(),
+        }
+    }
+
+    /// Add `cookie` header from the store, unless the request has one.
+    ///
+    /// The store is told whether the request is cross-site by `sec-fetch-site`. With a
+    /// per-request header order, `cookie` is placed where `common_header_order` has it.
+    pub(crate) fn apply_cookies(
+        &mut self,
+        store: &dyn CookieStore,
+        config: Option<&ImpersonationConfig>,
+    ) {
+        if self.headers.contains_key(COOKIE) {
+            return;
+        }
+
+        let cross_site = self
+            .headers
+            .get("sec-fetch-site")
+            .is_some_and(|site| site == "cross-site");
+        let context = match cross_site {
+            false => SiteContext::SameSite,
+            true if self.kind == RequestKind::Document && self.method.is_safe() => {
+                SiteContext::CrossSiteNavigation
+            }
+            true => SiteContext::CrossSite,
+        };
+        let Some(cookie) = store.cookies(&self.uri, context) else {
+            return;
+        };
+        self.headers.insert(COOKIE, cookie);
+
+        let Some(order) = self
+            .header_order
+            .as_mut()
+            .filter(|order| !order.iter().any(|h| h == COOKIE.as_str()))
+        else {
+            return;
+        };
+        let common_order = config.map_or(&[][..], |c| &c.common_header_order[..]);
+        let position = match common_order.iter().position(|h| h == COOKIE.as_str()) {
+            // after the last header placed before `cookie` by the common order
+            Some(idx) => order
+                .iter()
+                .rposition(|h| common_order[..idx].contains(h))
+                .map_or(0, |p| p + 1),
+            None => order.len(),
+        };
+        order.insert(position, COOKIE.as_str().to_string());
+    }
+
+    /// Convert into [`HttpRequestFfi`] to be sent with the given client, along with the
+    /// streaming part of the body to be uploaded
+    pub(crate) fn into_ffi(
+        mut self,
+        client_id: u64,
+        request_id: u64,
+    ) -> Result<(HttpRequestFfi, Option<body::Upload>), ErrorType> {
+        if self.timeout.is_some_and(|t| t.is_zero()) {
+            return Err(ErrorType::InvalidConfig(
+                "timeout must be greater than zero".to_string(),
+            ));
+        }
+
+        let pseudo_header_order = self.pseudo_header_order.unwrap_or_default();
+        validate_pseudo_header_order("pseudo_header_order", &pseudo_header_order)?;
+        let header_order = self.header_order.unwrap_or_default();
+        validate_header_order("header_order", &header_order)?;
+
+        if let Some(priority) = self.extensible_priority {
+            self.headers.insert(
+                HeaderName::from_static("priority"),
+                priority.header_value()?,
+            );
+        }
+
         let headers = self
             .headers
             .keys()
@@ -108,13 +323,168 @@ impl Request {
             })
             .collect();
 
+        let (body, content_length, upload) = self.body.unwrap_or_default().into_ffi();
+
         let req_ffi = HttpRequestFfi {
+            client_id,
+            request_id,
+            timeout_ms: duration_to_ms(self.timeout),
             url: self.uri.to_string(),
-            method,
-            body: self.body.unwrap_or_default(),
+            method: self.method.to_string(),
+            body,
+            streaming_body: upload.is_some(),
+            content_length,
             headers,
+            ech_config_list: Vec::new(),
+            has_priority: self.priority.is_some(),
+            priority: self.priority.unwrap_or(Http2PriorityParam {
+                stream_dep: 0,
+                exclusive: false,
+                weight: 0,
+            }),
+            pseudo_header_order,
+            header_order,
+            omitted_common_headers: self.omitted_common_headers,
         };
 
-        ReqwestxGoImpl::send(req_ffi).await.into_result()
+        Ok((req_ffi, upload))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extension_method() {
+        let uri: Uri = "https://example.com/dav/".parse().unwrap();
+        for method in ["PROPFIND", "PURGE", "CONNECT", "TRACE"] {
+            let method = Method::from_bytes(method.as_bytes()).unwrap();
+            let request = Request::new(uri.clone(), method.clone());
+            let (req_ffi, _) = request.into_ffi(1, 1).unwrap();
+            assert_eq!(req_ffi.method, method.as_str());
+        }
+    }
+
+    #[test]
+    fn test_priority() {
+        let uri: Uri = "https://example.com/app.js".parse().unwrap();
+        let (req_ffi, _) = Request::get(uri.clone()).into_ffi(1, 1).unwrap();
+        assert!(!req_ffi.has_priority);
+        assert!(req_ffi.headers.is_empty());
+
+        let request = Request::get(uri.clone())
+            .priority(Http2PriorityParam {
+                stream_dep: 3,
+                exclusive: false,
+                weight: 21,
+            })
+            .extensible_priority(ExtensiblePriority::new(1, false));
+        let (req_ffi, _) = request.into_ffi(1, 1).unwrap();
+        assert!(req_ffi.has_priority);
+        assert_eq!(req_ffi.priority.stream_dep, 3);
+        assert_eq!(req_ffi.headers[0].k, "priority");
+        assert_eq!(req_ffi.headers[0].v, vec!["u=1".to_string()]);
+
+        let request = Request::get(uri).extensible_priority(ExtensiblePriority::new(8, false));
+        assert!(matches!(
+            request.into_ffi(1, 1),
+            Err(ErrorType::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_header_order() {
+        let uri: Uri = "https://example.com/api".parse().unwrap();
+        let (req_ffi, _) = Request::post(uri.clone()).into_ffi(1, 1).unwrap();
+        assert!(req_ffi.pseudo_header_order.is_empty());
+        assert!(req_ffi.header_order.is_empty());
+
+        let request = Request::post(uri.clone())
+            .pseudo_header_order([":method", ":authority", ":scheme", ":path"])
+            .header_order(["content-length", "content-type", "origin"]);
+        let (req_ffi, _) = request.into_ffi(1, 1).unwrap();
+        assert_eq!(req_ffi.pseudo_header_order[1], ":authority");
+        assert_eq!(
+            req_ffi.header_order,
+            ["content-length", "content-type", "origin"]
+        );
+
+        for request in [
+            Request::post(uri.clone()).pseudo_header_order([":method", ":path"]),
+            Request::post(uri).header_order(["Content-Type"]),
+        ] {
+            assert!(matches!(
+                request.into_ffi(1, 1),
+                Err(ErrorType::InvalidConfig(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_request_kind() {
+        let uri: Uri = "https://example.com/api".parse().unwrap();
+        let config = crate::client::impersonate::profiles::chrome(131);
+
+        let mut request = Request::get(uri.clone());
+        request.apply_kind_headers(Some(&config));
+        let (req_ffi, _) = request.into_ffi(1, 1).unwrap();
+        assert!(req_ffi.headers.is_empty());
+        assert!(req_ffi.omitted_common_headers.is_empty());
+
+        let mut request = Request::get(uri)
+            .kind(RequestKind::Fetch)
+            .extensible_priority(ExtensiblePriority::new(4, false));
+        request.apply_kind_headers(Some(&config));
+        assert_eq!(request.headers["accept"], "*/*");
+        assert_eq!(request.headers["sec-fetch-mode"], "cors");
+        let (req_ffi, _) = request.into_ffi(1, 1).unwrap();
+        let priority = req_ffi.headers.iter().find(|h| h.k == "priority").unwrap();
+        assert_eq!(priority.v, vec!["u=4".to_string()]);
+        assert_eq!(
+            req_ffi.omitted_common_headers,
+            ["upgrade-insecure-requests", "sec-fetch-user"]
+        );
+    }
+
+    #[test]
+    fn test_apply_cookies() {
+        use crate::client::Jar;
+
+        let uri: Uri = "https://www.example.com/api".parse().unwrap();
+        let jar = Jar::new();
+        jar.add_cookie_str("sid=1; Path=/", &uri);
+        jar.add_cookie_str("strict=1; Path=/; SameSite=Strict", &uri);
+        let config = crate::client::impersonate::profiles::chrome(131);
+
+        let mut request = Request::post(uri.clone())
+            .kind(RequestKind::Fetch)
+            .header_order(["content-length", "origin", "user-agent", "priority"]);
+        request.apply_cookies(&jar, Some(&config));
+        assert_eq!(request.headers[COOKIE], "sid=1; strict=1");
+        assert_eq!(
+            request.header_order.unwrap(),
+            [
+                "content-length",
+                "origin",
+                "user-agent",
+                "cookie",
+                "priority"
+            ]
+        );
+
+        let mut request = Request::get(uri.clone());
+        request
+            .headers
+            .insert("sec-fetch-site", HeaderValue::from_static("cross-site"));
+        request.apply_cookies(&jar, Some(&config));
+        assert_eq!(request.headers[COOKIE], "sid=1");
+
+        let mut request = Request::get(uri);
+        request
+            .headers
+            .insert(COOKIE, HeaderValue::from_static("manual=1"));
+        request.apply_cookies(&jar, None);
+        assert_eq!(request.headers[COOKIE], "manual=1");
     }
 }
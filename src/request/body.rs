@@ -0,0 +1,271 @@
+use std::{fmt, io, path::Path, pin::Pin};
+
+use bytes::Bytes;
+use futures_core::Stream;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::ffi::{ReqwestxGo, ReqwestxGoImpl};
+
+/// Size of each chunk read from [`AsyncRead`] and sent to Go side
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Request body, either in memory or streamed to Go side chunk by chunk.
+///
+/// `Content-Length` will be set when the length is known, otherwise the body is sent
+/// with chunked transfer-encoding.
+pub struct Body {
+    inner: Inner,
+}
+
+enum Inner {
+    Full(Bytes),
+    Reader {
+        reader: Pin<Box<dyn AsyncRead + Send>>,
+        length: Option<u64>,
+    },
+    Stream {
+        stream: Pin<Box<dyn Stream<Item = Bytes> + Send>>,
+        length: Option<u64>,
+    },
+}
+
+impl Body {
+    /// Create an empty body
+    #[inline]
+    pub fn empty() -> Self {
+        Bytes::new().into()
+    }
+
+    /// Wrap an [`AsyncRead`], `length` should be the exact number of bytes to be read if known
+    pub fn from_reader<R>(reader: R, length: Option<u64>) -> Self
+    where
+        R: AsyncRead + Send + 'static,
+    {
+        Self {
+            inner: Inner::Reader {
+                reader: Box::pin(reader),
+                length,
+            },
+        }
+    }
+
+    /// Wrap a [`Stream`] of chunks, `length` should be the exact total size if known
+    pub fn wrap_stream<S>(stream: S, length: Option<u64>) -> Self
+    where
+        S: Stream<Item = Bytes> + Send + 'static,
+    {
+        Self {
+            inner: Inner::Stream {
+                stream: Box::pin(stream),
+                length,
+            },
+        }
+    }
+
+    /// Open the file and stream it as the body, with its size as the length
+    pub async fn file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = tokio::fs::File::open(path).await?;
+        let length = file.metadata().await?.len();
+
+        Ok(Self::from_reader(file, Some(length)))
+    }
+
+    /// Length of the body if known
+    pub fn content_length(&self) -> Option<u64> {
+        match &self.inner {
+            Inner::Full(data) => Some(data.len() as u64),
+            Inner::Reader { length, .. } | Inner::Stream { length, .. } => *length,
+        }
+    }
+
+    /// Return in-memory data, `None` if it's a streaming body
+    #[inline]
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match &self.inner {
+            Inner::Full(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// Clone in-memory data, `None` if it's a streaming body which can only be sent once
+    #[inline]
+    pub fn try_clone(&self) -> Option<Self> {
+        match &self.inner {
+            Inner::Full(data) => Some(data.clone().into()),
+            _ => None,
+        }
+    }
+
+    /// Split into in-memory data and the streaming part to be uploaded
+    pub(crate) fn into_ffi(self) -> (Vec<u8>, i64, Option<Upload>) {
+        let length = self
+            .content_length()
+            .map_or(-1, |len| i64::try_from(len).unwrap_or(i64::MAX));
+        match self.inner {
+            Inner::Full(data) => (data.into(), length, None),
+            inner => (Vec::new(), length, Some(Upload { inner })),
+        }
+    }
+}
+
+impl Default for Body {
+    #[inline]
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl fmt::Debug for Body {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match &self.inner {
+            Inner::Full(_) => "Full",
+            Inner::Reader { .. } => "Reader",
+            Inner::Stream { .. } => "Stream",
+        };
+        f.debug_struct("Body")
+            .field("kind", &kind)
+            .field("length", &self.content_length())
+            .finish()
+    }
+}
+
+impl From<Bytes> for Body {
+    #[inline]
+    fn from(value: Bytes) -> Self {
+        Self {
+            inner: Inner::Full(value),
+        }
+    }
+}
+
+impl From<Vec<u8>> for Body {
+    #[inline]
+    fn from(value: Vec<u8>) -> Self {
+        Bytes::from(value).into()
+    }
+}
+
+impl From<String> for Body {
+    #[inline]
+    fn from(value: String) -> Self {
+        Bytes::from(value).into()
+    }
+}
+
+impl From<&'static [u8]> for Body {
+    #[inline]
+    fn from(value: &'static [u8]) -> Self {
+        Bytes::from_static(value).into()
+    }
+}
+
+impl From<&'static str> for Body {
+    #[inline]
+    fn from(value: &'static str) -> Self {
+        Bytes::from_static(value.as_bytes()).into()
+    }
+}
+
+/// Streaming part of the body, written to Go side by [`Upload::run`]
+pub(crate) struct Upload {
+    inner: Inner,
+}
+
+impl Upload {
+    /// Write all chunks to the Go side pipe of the request, then close it.
+    ///
+    /// Stops early if Go side no longer accepts data, e.g. the request has failed or
+    /// the server has responded without reading the whole body.
+    pub(crate) async fn run(mut self, request_id: u64) {
+        let error = loop {
+            let chunk = match self.next_chunk().await {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => break String::new(),
+                Err(e) => break e.to_string(),
+            };
+
+            if ReqwestxGoImpl::write_body(request_id, chunk)
+                .await
+                .into_result()
+                .is_err()
+            {
+                return;
+            }
+        };
+
+        // Empty error means EOF, otherwise the request fails with it.
+        let _ = ReqwestxGoImpl::close_upload(request_id, error);
+    }
+
+    async fn next_chunk(&mut self) -> io::Result<Option<Vec<u8>>> {
+        match &mut self.inner {
+            Inner::Full(_) => unreachable!("in-memory body is sent directly"),
+            Inner::Reader { reader, .. } => {
+                let mut buf = vec![0; CHUNK_SIZE];
+                let n = reader.read(&mut buf).await?;
+                if n == 0 {
+                    return Ok(None);
+                }
+                buf.truncate(n);
+                Ok(Some(buf))
+            }
+            Inner::Stream { stream, .. } => loop {
+                match std::future::poll_fn(|cx| stream.as_mut().poll_next(cx)).await {
+                    Some(chunk) if chunk.is_empty() => continue,
+                    Some(chunk) => return Ok(Some(chunk.into())),
+                    None => return Ok(None),
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Chunks(Vec<Bytes>);
+
+    impl Stream for Chunks {
+        type Item = Bytes;
+
+        fn poll_next(
+            mut self: Pin<&mut Self>,
+            _: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Option<Self::Item>> {
+            std::task::Poll::Ready((!self.0.is_empty()).then(|| self.0.remove(0)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_into_ffi() {
+        let (data, length, upload) = Body::from("hello").into_ffi();
+        assert_eq!(data, b"hello");
+        assert_eq!(length, 5);
+        assert!(upload.is_none());
+
+        let reader: &'static [u8] = &[0; CHUNK_SIZE + 1];
+        let (data, length, upload) = Body::from_reader(reader, None).into_ffi();
+        assert!(data.is_empty());
+        assert_eq!(length, -1);
+        let mut upload = upload.unwrap();
+        assert_eq!(
+            upload.next_chunk().await.unwrap().unwrap().len(),
+            CHUNK_SIZE
+        );
+        assert_eq!(upload.next_chunk().await.unwrap().unwrap().len(), 1);
+        assert!(upload.next_chunk().await.unwrap().is_none());
+
+        let stream = Chunks(vec![
+            Bytes::from_static(b"a"),
+            Bytes::new(),
+            Bytes::from_static(b"b"),
+        ]);
+        let (_, length, upload) = Body::wrap_stream(stream, Some(2)).into_ffi();
+        assert_eq!(length, 2);
+        let mut upload = upload.unwrap();
+        assert_eq!(upload.next_chunk().await.unwrap().unwrap(), b"a");
+        assert_eq!(upload.next_chunk().await.unwrap().unwrap(), b"b");
+        assert!(upload.next_chunk().await.unwrap().is_none());
+    }
+}
@@ -1,8 +1,15 @@
+mod body;
+
+pub use body::Body;
+
 use http::{HeaderMap, HeaderName, HeaderValue, Response, Version};
 
-use crate::ffi::HttpResponseFfi;
+use crate::{
+    client::{impersonate::GreaseValues, EchStatus},
+    ffi::HttpResponseFfi,
+};
 
-impl From<HttpResponseFfi> for Response<Vec<u8>> {
+impl From<HttpResponseFfi> for Response<Body> {
     fn from(value: HttpResponseFfi) -> Self {
         let version = match (value.proto_major, value.proto_minor) {
             (1, 0) => Version::HTTP_10,
@@ -15,7 +22,7 @@ impl From<HttpResponseFfi> for Response<Vec<u8>> {
         let mut response = Response::builder()
             .status(value.code as u16)
             .version(version)
-            .body(value.data.clone()) // ? should we clone it here?
+            .body(Body::streaming(value.body_id, value.content_length))
             .unwrap();
 
         let mut headers = HeaderMap::with_capacity(value.headers.len());
@@ -33,6 +40,18 @@ impl From<HttpResponseFfi> for Response<Vec<u8>> {
         });
         let _ = std::mem::replace(response.headers_mut(), headers);
 
+        // `Rejected` is told apart by the client, which knows whether ECH was offered
+        response.extensions_mut().insert(if value.ech_accepted {
+            EchStatus::Accepted
+        } else {
+            EchStatus::NotOffered
+        });
+        if !value.grease_values.is_empty() {
+            response
+                .extensions_mut()
+                .insert(GreaseValues::from_ffi(&value.grease_values));
+        }
+
         response
     }
 }
@@ -0,0 +1,290 @@
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use bytes::{Bytes, BytesMut};
+use http_body::{Frame, SizeHint};
+
+use crate::{
+    client::Client,
+    error::ErrorType,
+    ffi::{GoResultBodyChunkFfi, ReqwestxGo, ReqwestxGoImpl},
+};
+
+/// Response body, streamed from Go side chunk by chunk.
+///
+/// Implements both [`futures_core::Stream`] and [`http_body::Body`], or use
+/// [`Body::bytes`] / [`Body::text`] to read it fully.
+pub struct Body {
+    inner: Inner,
+}
+
+enum Inner {
+    Empty,
+    Full(Option<Bytes>),
+    Streaming(Streaming),
+}
+
+impl Body {
+    /// Create an empty body
+    #[inline]
+    pub fn empty() -> Self {
+        Self {
+            inner: Inner::Empty,
+        }
+    }
+
+    /// Create a body kept by Go side, `content_length` is -1 if unknown
+    pub(crate) fn streaming(body_id: u64, content_length: i64) -> Self {
+        Self {
+            inner: Inner::Streaming(Streaming {
+                body_id,
+                content_length: u64::try_from(content_length).ok(),
+                pending: None,
+                done: false,
+                client: None,
+            }),
+        }
+    }
+
+    /// Keep the client alive until the body is finished or closed, as Go side releases
+    /// the bodies of a client along with it
+    pub(crate) fn keep_client(&mut self, client: &Client) {
+        if let Inner::Streaming(streaming) = &mut self.inner {
+            if !streaming.done {
+                streaming.client = Some(client.clone());
+            }
+        }
+    }
+
+    /// Read the whole body into memory
+    pub async fn bytes(mut self) -> Result<Bytes, ErrorType> {
+        let Some(first) = self.next_chunk().await.transpose()? else {
+            return Ok(Bytes::new());
+        };
+        let Some(second) = self.next_chunk().await.transpose()? else {
+            return Ok(first);
+        };
+
+        let mut buf = BytesMut::with_capacity(first.len() + second.len());
+        buf.extend_from_slice(&first);
+        buf.extend_from_slice(&second);
+        while let Some(chunk) = self.next_chunk().await.transpose()? {
+            buf.extend_from_slice(&chunk);
+        }
+
+        Ok(buf.freeze())
+    }
+
+    /// Read the whole body into memory as UTF-8 string, invalid sequences are replaced
+    /// with `U+FFFD REPLACEMENT CHARACTER`
+    pub async fn text(self) -> Result<String, ErrorType> {
+        let bytes = self.bytes().await?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    #[inline]
+    async fn next_chunk(&mut self) -> Option<Result<Bytes, ErrorType>> {
+        std::future::poll_fn(|cx| self.poll_chunk(cx)).await
+    }
+
+    fn poll_chunk(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, ErrorType>>> {
+        match &mut self.inner {
+            Inner::Empty => Poll::Ready(None),
+            Inner::Full(data) => Poll::Ready(data.take().map(Ok)),
+            Inner::Streaming(streaming) => streaming.poll_chunk(cx),
+        }
+    }
+
+    fn is_end(&self) -> bool {
+        match &self.inner {
+            Inner::Empty => true,
+            Inner::Full(data) => data.is_none(),
+            Inner::Streaming(streaming) => streaming.done,
+        }
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        match &self.inner {
+            Inner::Empty => SizeHint::with_exact(0),
+            Inner::Full(data) => SizeHint::with_exact(data.as_ref().map_or(0, |d| d.len() as u64)),
+            Inner::Streaming(Streaming {
+                content_length: Some(len),
+                done: false,
+                ..
+            }) => SizeHint::with_exact(*len),
+            Inner::Streaming(Streaming { done: true, .. }) => SizeHint::with_exact(0),
+            Inner::Streaming(_) => SizeHint::default(),
+        }
+    }
+}
+
+impl Default for Body {
+    #[inline]
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl fmt::Debug for Body {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.inner {
+            Inner::Empty => f.write_str("Body(Empty)"),
+            Inner::Full(_) => f.write_str("Body(Full)"),
+            Inner::Streaming(streaming) => f
+                .debug_struct("Body")
+                .field("body_id", &streaming.body_id)
+                .field("content_length", &streaming.content_length)
+                .field("done", &streaming.done)
+                .finish(),
+        }
+    }
+}
+
+impl From<Bytes> for Body {
+    #[inline]
+    fn from(value: Bytes) -> Self {
+        Self {
+            inner: Inner::Full(Some(value)),
+        }
+    }
+}
+
+impl From<Vec<u8>> for Body {
+    #[inline]
+    fn from(value: Vec<u8>) -> Self {
+        Bytes::from(value).into()
+    }
+}
+
+impl From<String> for Body {
+    #[inline]
+    fn from(value: String) -> Self {
+        Bytes::from(value).into()
+    }
+}
+
+impl futures_core::Stream for Body {
+    type Item = Result<Bytes, ErrorType>;
+
+    #[inline]
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_chunk(cx)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.is_end() {
+            (0, Some(0))
+        } else {
+            (0, None)
+        }
+    }
+}
+
+impl http_body::Body for Body {
+    type Data = Bytes;
+    type Error = ErrorType;
+
+    #[inline]
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        self.get_mut()
+            .poll_chunk(cx)
+            .map(|chunk| chunk.map(|chunk| chunk.map(Frame::data)))
+    }
+
+    #[inline]
+    fn is_end_stream(&self) -> bool {
+        self.is_end()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> SizeHint {
+        Body::size_hint(self)
+    }
+}
+
+/// Body kept by Go side, read chunk by chunk through `read_body`
+struct Streaming {
+    body_id: u64,
+    /// Remaining length of the body, `None` if unknown
+    content_length: Option<u64>,
+    pending: Option<Pin<Box<dyn Future<Output = GoResultBodyChunkFfi> + Send>>>,
+    done: bool,
+    /// Client of the request, dropped after the body is finished or closed
+    client: Option<Client>,
+}
+
+impl Streaming {
+    fn poll_chunk(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, ErrorType>>> {
+        loop {
+            if self.done {
+                return Poll::Ready(None);
+            }
+
+            let body_id = self.body_id;
+            let pending = self
+                .pending
+                .get_or_insert_with(|| Box::pin(ReqwestxGoImpl::read_body(body_id)));
+            let result = ready!(pending.as_mut().poll(cx));
+            self.pending = None;
+
+            match result.into_result() {
+                Ok(chunk) => {
+                    self.done = chunk.eof;
+                    if self.done {
+                        self.client = None;
+                    }
+                    self.content_length = self
+                        .content_length
+                        .map(|len| len.saturating_sub(chunk.data.len() as u64));
+                    if !chunk.data.is_empty() {
+                        return Poll::Ready(Some(Ok(Bytes::from(chunk.data))));
+                    }
+                }
+                Err(e) => {
+                    // Go side has released the body
+                    self.done = true;
+                    self.client = None;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Streaming {
+    fn drop(&mut self) {
+        if !self.done {
+            // Also cancels the request context on Go side.
+            let _ = ReqwestxGoImpl::close_body(self.body_id);
+        }
+        // `client` is dropped after the body is closed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_full_body() {
+        let body = Body::from("hello world".to_string());
+        assert_eq!(http_body::Body::size_hint(&body).exact(), Some(11));
+        assert!(!http_body::Body::is_end_stream(&body));
+        assert_eq!(body.text().await.unwrap(), "hello world");
+
+        let mut body = Body::from(vec![1, 2, 3]);
+        let chunk = body.next_chunk().await.unwrap().unwrap();
+        assert_eq!(chunk.as_ref(), &[1, 2, 3]);
+        assert!(body.next_chunk().await.is_none());
+        assert!(http_body::Body::is_end_stream(&body));
+
+        assert!(Body::empty().bytes().await.unwrap().is_empty());
+    }
+}